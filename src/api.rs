@@ -0,0 +1,279 @@
+use crate::config::CONFIG;
+use anyhow::{Context, Result};
+use log::{debug, error, info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+
+/// A class or member every submission has to declare.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct ApiRequirement {
+    /// Class name, as passed to `javap`.
+    pub class: String,
+    /// Declaration of a method or field, e.g. `public static int countVowels(String s)`.
+    /// Without it only the class itself is required.
+    #[serde(default)]
+    pub member: Option<String>,
+    /// Points awarded when the requirement is met.
+    #[serde(default)]
+    pub points: u64,
+}
+
+/// A requirement a submission does not meet.
+#[derive(Serialize, Clone, Debug)]
+pub struct ApiFinding {
+    pub class: String,
+    pub member: Option<String>,
+    pub points: u64,
+    #[serde(flatten)]
+    pub problem: ApiProblem,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum ApiProblem {
+    /// The class was not compiled, so none of its members can be checked.
+    MissingClass,
+    /// No member with the required name exists.
+    MissingMember,
+    /// Members with the required name exist, but none with the required declaration.
+    Mismatch { found: Vec<String> },
+}
+
+/// API conformance of one submission.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ApiReport {
+    pub points_awarded: u64,
+    pub findings: Vec<ApiFinding>,
+}
+
+const MODIFIERS: [&str; 14] = [
+    "public",
+    "protected",
+    "private",
+    "static",
+    "final",
+    "abstract",
+    "synchronized",
+    "native",
+    "transient",
+    "volatile",
+    "strictfp",
+    "default",
+    "sealed",
+    "non-sealed",
+];
+
+/// Package qualifiers such as `java.lang.`, which `javap` prints and people rarely write.
+static QUALIFIER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:[a-z_][\w$]*\.)+([A-Z][\w$]*)").expect("static regex"));
+
+/// A declaration reduced to what can be compared between `javap` output and the config.
+#[derive(Debug, PartialEq, Eq)]
+struct Signature {
+    modifiers: BTreeSet<String>,
+    name: String,
+    /// Everything but the modifiers, with parameter names and `throws` removed.
+    shape: String,
+}
+
+impl Signature {
+    fn parse(decl: &str) -> Option<Self> {
+        let decl = decl.trim().trim_end_matches(';').trim();
+        let decl = decl.split(" throws ").next().unwrap_or(decl);
+        let decl = QUALIFIER.replace_all(decl, "$1");
+        let (head, params) = match decl.split_once('(') {
+            Some((head, rest)) => (head, Some(rest.rsplit_once(')')?.0)),
+            None => (decl.as_ref(), None),
+        };
+        let mut modifiers = BTreeSet::new();
+        let mut rest = vec![];
+        for word in split_top_level(head, ' ') {
+            if rest.is_empty() && MODIFIERS.contains(&word.as_str()) {
+                modifiers.insert(word);
+            } else {
+                rest.push(word);
+            }
+        }
+        let name = rest.last()?.clone();
+        let mut shape = rest.join(" ");
+        if let Some(params) = params {
+            let types: Vec<String> = split_top_level(params, ',')
+                .iter()
+                .map(|param| param_type(param))
+                .collect();
+            shape = format!("{shape}({})", types.join(","));
+        }
+        Some(Self {
+            modifiers,
+            name,
+            shape: shape.replace(' ', ""),
+        })
+    }
+}
+
+/// Splits on `sep` outside of generic brackets, dropping empty pieces.
+fn split_top_level(s: &str, sep: char) -> Vec<String> {
+    let mut ret = vec![];
+    let mut depth = 0usize;
+    let mut current = String::new();
+    for c in s.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if c == sep && depth == 0 {
+            ret.push(current.trim().to_owned());
+            current.clear();
+        } else {
+            current.push(c);
+        }
+    }
+    ret.push(current.trim().to_owned());
+    ret.retain(|piece| !piece.is_empty());
+    ret
+}
+
+/// Type of a parameter declaration, without `final`, annotations or its name.
+fn param_type(param: &str) -> String {
+    let mut words: Vec<String> = split_top_level(param, ' ')
+        .into_iter()
+        .filter(|word| word != "final" && !word.starts_with('@'))
+        .collect();
+    if words.len() > 1 {
+        words.pop();
+    }
+    words.join(" ")
+}
+
+/// Checks every submission in `dirs` against `CONFIG.api`. Submissions are expected to be compiled.
+pub async fn check_dirs(dirs: Vec<PathBuf>) -> Result<HashMap<PathBuf, ApiReport>> {
+    let semaphore = Arc::new(Semaphore::new(
+        usize::try_from(CONFIG.threads.max(1)).context("thread count exceeds usize range")?,
+    ));
+    let mut handles = vec![];
+    for dir in dirs {
+        let semaphore = Arc::clone(&semaphore);
+        handles.push(tokio::task::spawn(async move {
+            let report = match semaphore.acquire().await {
+                Ok(_permit) => check_dir(&dir, &CONFIG.api).await,
+                Err(e) => {
+                    error!("Failed to acquire semaphore: {e}");
+                    ApiReport::default()
+                }
+            };
+            (dir, report)
+        }));
+    }
+    let mut ret = HashMap::new();
+    for handle in handles {
+        let (dir, report) = handle.await.context("API check task panicked")?;
+        ret.insert(dir, report);
+    }
+    info!(
+        "API check complete: {} submission(s) missing required members.",
+        ret.values()
+            .filter(|report| !report.findings.is_empty())
+            .count()
+    );
+    Ok(ret)
+}
+
+pub async fn check_dir(dir: &Path, requirements: &[ApiRequirement]) -> ApiReport {
+    let mut members: HashMap<&str, Option<Vec<String>>> = HashMap::new();
+    for requirement in requirements {
+        if !members.contains_key(requirement.class.as_str()) {
+            let found = javap(dir, &requirement.class).await;
+            members.insert(&requirement.class, found);
+        }
+    }
+    let mut report = ApiReport::default();
+    for requirement in requirements {
+        let problem = match &members[requirement.class.as_str()] {
+            None => Some(ApiProblem::MissingClass),
+            Some(declared) => requirement
+                .member
+                .as_deref()
+                .and_then(|member| compare(member, declared)),
+        };
+        match problem {
+            None => report.points_awarded += requirement.points,
+            Some(problem) => report.findings.push(ApiFinding {
+                class: requirement.class.clone(),
+                member: requirement.member.clone(),
+                points: requirement.points,
+                problem,
+            }),
+        }
+    }
+    debug!(
+        "{}: {} API finding(s).",
+        dir.display(),
+        report.findings.len()
+    );
+    report
+}
+
+fn compare(member: &str, declared: &[String]) -> Option<ApiProblem> {
+    let Some(required) = Signature::parse(member) else {
+        warn!("Could not parse required member `{member}`.");
+        return Some(ApiProblem::MissingMember);
+    };
+    let same_name: Vec<&String> = declared
+        .iter()
+        .filter(|decl| Signature::parse(decl).is_some_and(|sig| sig.name == required.name))
+        .collect();
+    if same_name.is_empty() {
+        Some(ApiProblem::MissingMember)
+    } else if same_name
+        .iter()
+        .any(|decl| Signature::parse(decl).is_some_and(|sig| sig == required))
+    {
+        None
+    } else {
+        Some(ApiProblem::Mismatch {
+            found: same_name.into_iter().cloned().collect(),
+        })
+    }
+}
+
+/// Member declarations of `class` as printed by `javap -p`, or `None` if it was not compiled.
+async fn javap(dir: &Path, class: &str) -> Option<Vec<String>> {
+    let out = match Command::new("javap")
+        .arg("-p")
+        .arg("-cp")
+        .arg(dir)
+        .arg(class)
+        .kill_on_drop(true)
+        .output()
+        .await
+    {
+        Ok(out) => out,
+        Err(e) => {
+            error!("Failed to run javap: {e}");
+            return None;
+        }
+    };
+    if !out.status.success() {
+        debug!(
+            "javap could not load {class} in {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&out.stderr)
+        );
+        return None;
+    }
+    // the first lines are the source file and the class header; members are indented.
+    Some(
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .filter(|line| line.starts_with(' '))
+            .map(|line| line.trim().to_owned())
+            .collect(),
+    )
+}
@@ -11,6 +11,18 @@ use anyhow::{Ok, Result};
 use walkdir::WalkDir;
 
 use crate::config;
+use crate::executable::Language;
+
+pub mod ast;
+pub mod bytecode;
+pub mod c;
+pub mod lexer;
+pub mod metrics;
+pub mod python;
+pub mod require;
+pub mod rules;
+pub mod style;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum Type {
@@ -18,9 +30,16 @@ pub enum Type {
     Static,
 }
 
-pub async fn check_dirs(paths: Vec<PathBuf>) -> Result<HashMap<PathBuf, Vec<IllegalExpr>>> {
+/// Checks every file in `paths`, and measures the source files among them.
+pub async fn check_dirs(
+    paths: Vec<PathBuf>,
+) -> Result<(
+    HashMap<PathBuf, Vec<IllegalExpr>>,
+    HashMap<PathBuf, metrics::Metrics>,
+)> {
     crate::config::get_config()?;
     let results = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+    let measured = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
     let max_threads = config::get_config()?.threads;
     let semaphore = Arc::new(Semaphore::new(usize::try_from(max_threads)?));
     let errors = Arc::new(tokio::sync::Mutex::new(Vec::<(PathBuf, String)>::new()));
@@ -68,7 +87,10 @@ pub async fn check_dirs(paths: Vec<PathBuf>) -> Result<HashMap<PathBuf, Vec<Ille
         >::clone(&errors);
         let op = Arc::<tokio::sync::Mutex<indicatif::ProgressBar>>::clone(&op);
         let mp = Arc::<indicatif::MultiProgress>::clone(&mp);
-        let handle = tokio::spawn(changefile_prog(results, semaphore, entry, errors, op, mp));
+        let measured = Arc::clone(&measured);
+        let handle = tokio::spawn(changefile_prog(
+            results, measured, semaphore, entry, errors, op, mp,
+        ));
         handles.push(handle);
     }
     op.lock().await.finish_and_clear();
@@ -86,11 +108,13 @@ pub async fn check_dirs(paths: Vec<PathBuf>) -> Result<HashMap<PathBuf, Vec<Ille
         "Static analysis complete: {} file(s) flagged out of {} inspected.",
         flagged, total_entries
     );
-    Ok(ret)
+    let measured = core::mem::take(&mut *measured.lock().await);
+    Ok((ret, measured))
 }
 
 pub async fn changefile_prog(
     results: Arc<tokio::sync::Mutex<HashMap<PathBuf, Vec<IllegalExpr>>>>,
+    measured: Arc<tokio::sync::Mutex<HashMap<PathBuf, metrics::Metrics>>>,
     semaphore: Arc<Semaphore>,
     entry: PathBuf,
     errors: Arc<tokio::sync::Mutex<Vec<(PathBuf, String)>>>,
@@ -106,6 +130,13 @@ pub async fn changefile_prog(
     //        ?,
     //);
     //prog.enable_steady_tick(Duration::from_millis(50));
+    if let Some(ext) = entry.extension().and_then(|ext| ext.to_str())
+        && metrics::is_source(ext)
+        && let Result::Ok(src) = std::fs::read_to_string(&entry)
+    {
+        let found = metrics::measure(&src, &Language::from(ext));
+        measured.lock().await.insert(entry.clone(), found);
+    }
     let _ = changefile(results, semaphore, entry, errors).await;
     //prog.finish_and_clear();
     op.lock().await.inc(1);
@@ -198,26 +229,65 @@ async fn changefile(
 pub fn check_file(path: std::path::PathBuf) -> Result<Vec<IllegalExpr>> {
     debug!("checking {path:?}");
     let cfg = crate::config::get_config()?;
-    match cfg.checker {
-        Type::AST => {
-            warn!("AST checker is not supported yet. falling back to static analysis.");
-            static_check::check(&path)
-        }
-        Type::Static => static_check::check(&path),
+    let mut ret = match cfg.checker {
+        Type::AST => ast::check(&path)?,
+        Type::Static => static_check::check(&path)?,
+    };
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+    if !["java", "py", "c", "cpp"].contains(&ext) {
+        return Ok(ret);
+    }
+    let src = std::fs::read_to_string(&path)?;
+    if let Some(allowed) = &cfg.imports {
+        ret.extend(match ext {
+            "java" => ast::check_imports(&path, &src, allowed),
+            "py" => python::check_imports(&path, &src, allowed),
+            _ => c::check_includes(&path, &src, allowed),
+        });
     }
+    let prohibited = static_check::prohibited()?;
+    if prohibited.contains(&static_check::Allow::Obfuscation) {
+        ret.extend(match ext {
+            "java" => ast::check_obfuscation(&path, &src, &prohibited),
+            "py" => python::check_obfuscation(&path, &src),
+            _ => vec![],
+        });
+    }
+    ret.sort_by_key(|expr| (expr.loc.1, expr.loc.0));
+    Ok(ret)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub struct IllegalExpr {
+    /// The source line the expression is on.
     pub content: Option<String>,
     pub violates: Option<static_check::Allow>,
+    /// Name of the rule or check that matched.
+    pub rule: Option<String>,
+    pub message: Option<String>,
+    pub severity: rules::Severity,
+    pub action: rules::Action,
     pub loc: (usize, usize),
+    /// Position just past the offending expression, in the same (column, line) order as `loc`.
+    pub end: (usize, usize),
     pub path: PathBuf,
 }
 
+/// Line `line` (zero-based) of `src` without surrounding whitespace.
+pub fn source_line(src: &str, line: usize) -> String {
+    src.lines().nth(line).unwrap_or_default().trim().to_owned()
+}
+
 pub mod static_check {
-    use std::{collections::HashSet, fs::File, io::Read as _, path::PathBuf};
+    use std::{
+        collections::HashSet,
+        fs::read_to_string,
+        path::{Path, PathBuf},
+    };
 
     use anyhow::Result;
     use log::warn;
@@ -226,10 +296,14 @@ pub mod static_check {
 
     use crate::executable::Language;
 
-    use super::IllegalExpr;
-    pub fn check(path: &PathBuf) -> Result<Vec<IllegalExpr>> {
+    use super::lexer::{Pos, Syntax, Token, TokenKind, tokenize};
+    use super::rules::{self, Rule};
+    use super::{IllegalExpr, source_line};
+    use regex::Regex;
+    use serde::{Deserialize, Serialize};
+    /// Categories the configuration does not allow.
+    pub fn prohibited() -> Result<Vec<Allow>> {
         let allowcfg = crate::config::get_config()?.allow.clone();
-        let lang: Language = path.extension().unwrap().to_str().unwrap().into();
         let mut allowed = HashSet::new();
         for i in allowcfg {
             allowed.insert(match Allow::from_str(i.as_str()).first() {
@@ -243,41 +317,136 @@ pub mod static_check {
         if allowed.contains(&Allow::All) {
             return Ok(vec![]);
         }
-        let prohibited: Vec<Allow> = Allow::iter().filter(|el| !allowed.contains(el)).collect();
-        let mut prohibited_str: Vec<(Allow, &str)> = Vec::new();
-        for i in &prohibited {
-            for j in i.get_prohibited(&lang.clone()) {
-                prohibited_str.push((i.clone(), j));
-            }
+        // a case that expects a specific exit code needs the program to call `System.exit`.
+        if crate::config::get_config()?
+            .testcases
+            .iter()
+            .any(|case| case.exit.is_some())
+        {
+            allowed.insert(Allow::Exit);
         }
-        let mut f = File::open(path)?;
-        let mut s: String = String::new();
-        let _ = f.read_to_string(&mut s);
-        let mut illegal: Vec<(usize, Allow)> = vec![];
-        for i in prohibited_str {
-            if let Some(s) = s.find(i.1) {
-                illegal.push((s, i.0.clone()))
+        // `All` only bundles the other categories, so it must not override individual allows.
+        Ok(Allow::iter()
+            .filter(|el| *el != Allow::All && !allowed.contains(el))
+            .collect())
+    }
+
+    pub fn check(path: &PathBuf) -> Result<Vec<IllegalExpr>> {
+        let lang: Language = path.extension().unwrap().to_str().unwrap().into();
+        let prohibited = prohibited()?;
+        let src = read_to_string(path)?;
+        Ok(check_source(
+            path,
+            &src,
+            &lang,
+            &rules::active(&lang, &prohibited),
+        ))
+    }
+
+    pub fn check_source(
+        path: &Path,
+        src: &str,
+        lang: &Language,
+        rules: &[&Rule],
+    ) -> Vec<IllegalExpr> {
+        let syntax = Syntax::of(lang);
+        // comments and literals never produce tokens, so they cannot match.
+        let tokens = tokenize(src, &syntax);
+        let mut ret = vec![];
+        // rules with only a `resolved` name are for the AST checker.
+        for rule in rules.iter().filter(|rule| !rule.pattern.is_empty()) {
+            let found = occurrences(src, &tokens, &syntax, &rule.pattern, rule.regex());
+            for (start, end) in found {
+                ret.push(IllegalExpr {
+                    loc: (start.1, start.0),
+                    end: (end.1, end.0),
+                    content: Some(source_line(src, start.0)),
+                    rule: Some(rule.name.clone()),
+                    message: rule.message.clone(),
+                    severity: rule.severity,
+                    action: rule.action(),
+                    path: path.to_path_buf(),
+                    violates: rule.category.clone(),
+                });
             }
         }
-        let mut ret = vec![];
-        let mut indents: Vec<usize> = vec![];
-        for i in s.split('\n') {
-            indents.push(i.len());
+        ret.sort_by_key(|expr| (expr.loc.1, expr.loc.0));
+        ret
+    }
+
+    /// Start and end of every match of `pattern` in the tokens of `src`, or of `regex` if given.
+    pub fn occurrences(
+        src: &str,
+        tokens: &[Token],
+        syntax: &Syntax,
+        pattern: &str,
+        regex: Option<&Regex>,
+    ) -> Vec<(Pos, Pos)> {
+        match regex {
+            Some(re) => {
+                let code = code_only(src, tokens);
+                re.find_iter(&code)
+                    .map(|m| (pos_of(&code, m.start()), pos_of(&code, m.end())))
+                    .collect()
+            }
+            None => {
+                let needle = tokenize(pattern, syntax);
+                find_tokens(tokens, &needle)
+                    .map(|window| (window[0].start, window[window.len() - 1].end))
+                    .collect()
+            }
         }
-        for i in illegal {
-            let mut it = indents.iter();
-            let mut idts = 0;
-            while it.next().unwrap() > &i.0 {
-                idts += 1;
+    }
+
+    /// `src` with everything but code tokens blanked out, so regex rules see the same code the
+    /// token rules do while positions stay where they were.
+    fn code_only(src: &str, tokens: &[Token]) -> String {
+        let mut lines: Vec<Vec<char>> = src
+            .lines()
+            .map(|line| vec![' '; line.chars().count()])
+            .collect();
+        for token in tokens {
+            if matches!(token.kind, TokenKind::Str | TokenKind::Char) {
+                continue;
+            }
+            if let Some(line) = lines.get_mut(token.start.0) {
+                for (offset, c) in token.text.chars().enumerate() {
+                    if let Some(slot) = line.get_mut(token.start.1 + offset) {
+                        *slot = c;
+                    }
+                }
             }
-            ret.push(IllegalExpr {
-                loc: (i.0 - indents[idts], idts),
-                content: None,
-                path: path.clone(),
-                violates: Some(i.1),
-            });
         }
-        Ok(ret)
+        lines
+            .into_iter()
+            .map(String::from_iter)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Line and column of byte offset `idx` in `text`.
+    fn pos_of(text: &str, idx: usize) -> Pos {
+        let before = &text[..idx];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (
+            before.matches('\n').count(),
+            before[line_start..].chars().count(),
+        )
+    }
+
+    /// Every run of tokens in `haystack` with the same text as `needle`. Patterns are matched
+    /// on whole tokens, so `Thread` does not match `Threads`.
+    pub fn find_tokens<'a>(
+        haystack: &'a [Token],
+        needle: &'a [Token],
+    ) -> impl Iterator<Item = &'a [Token]> {
+        haystack.windows(needle.len().max(1)).filter(move |window| {
+            !needle.is_empty()
+                && window
+                    .iter()
+                    .zip(needle)
+                    .all(|(token, expected)| token.text == expected.text)
+        })
     }
 
     pub trait Prohibit {
@@ -306,7 +475,7 @@ pub mod static_check {
         }
     */
 
-    #[derive(PartialEq, Eq, Hash, Debug, Clone, EnumIter, AsRefStr)]
+    #[derive(PartialEq, Eq, Hash, Debug, Clone, EnumIter, AsRefStr, Serialize, Deserialize)]
     #[non_exhaustive]
     pub enum Allow {
         FileIO,
@@ -329,6 +498,8 @@ pub mod static_check {
         Import,
         Ctypes,
         Pickle,
+        Exit,
+        Obfuscation,
         Unknown,
         All,
     }
@@ -342,248 +513,5 @@ pub mod static_check {
             }
             ret
         }
-        fn get_prohibited(&self, lang: &Language) -> Vec<&'static str> {
-            match lang {
-                Language::Unknown(_) | Language::Guess => vec![],
-                Language::C => match &self {
-                    Self::SystemCall => {
-                        vec![
-                            "fork", "exec", "system", "popen", "vfork", "execl", "execlp",
-                            "execle", "execv", "execvp", "execve",
-                        ]
-                    }
-                    Self::FileIO => vec!["fopen", "fread", "fwrite", "fclose"],
-                    Self::Network => vec!["socket", "bind", "connect", "recv", "send"],
-                    Self::Assembly => vec!["asm", "__asm__"],
-                    Self::Signal => vec!["signal", "raise"],
-                    Self::Process => vec!["wait", "waitpid"],
-                    Self::All => {
-                        vec![
-                            "fork", "exec", "system", "popen", "vfork", "execl", "execlp",
-                            "execle", "execv", "execvp", "execve", "fopen", "fread", "fwrite",
-                            "fclose", "socket", "bind", "connect", "recv", "send", "asm",
-                            "__asm__", "signal", "raise", "wait", "waitpid",
-                        ]
-                    }
-                    Self::SysAccess
-                    | Self::Runtime
-                    | Self::Threading
-                    | Self::Reflection
-                    | Self::ProcessExec
-                    | Self::Unsafe
-                    | Self::FFI
-                    | Self::Command
-                    | Self::OsAccess
-                    | Self::Eval
-                    | Self::Exec
-                    | Self::Import
-                    | Self::Ctypes
-                    | Self::Pickle
-                    | Self::Unknown => vec![],
-                },
-                Language::Cpp => match &self {
-                    Self::SystemCall => {
-                        vec![
-                            "fork", "exec", "system", "popen", "vfork", "execl", "execlp",
-                            "execle", "execv", "execvp", "execve",
-                        ]
-                    }
-                    Self::FileIO => vec!["fopen", "fread", "fwrite", "fclose"],
-                    Self::Network => vec!["socket", "bind", "connect", "recv", "send"],
-                    Self::Assembly => vec!["asm", "__asm__"],
-                    Self::Signal => vec!["signal", "raise"],
-                    Self::Process => vec!["wait", "waitpid"],
-                    Self::All => {
-                        vec![
-                            "fork", "exec", "system", "popen", "vfork", "execl", "execlp",
-                            "execle", "execv", "execvp", "execve", "fopen", "fread", "fwrite",
-                            "fclose", "socket", "bind", "connect", "recv", "send", "asm",
-                            "__asm__", "signal", "raise", "wait", "waitpid",
-                        ]
-                    }
-                    Self::SysAccess
-                    | Self::Runtime
-                    | Self::Threading
-                    | Self::Reflection
-                    | Self::ProcessExec
-                    | Self::Unsafe
-                    | Self::FFI
-                    | Self::Command
-                    | Self::OsAccess
-                    | Self::Eval
-                    | Self::Exec
-                    | Self::Import
-                    | Self::Ctypes
-                    | Self::Pickle
-                    | Self::Unknown => vec![],
-                },
-
-                Language::Rust => match &self {
-                    Self::Unsafe => vec!["unsafe"],
-                    Self::FileIO => vec!["std::fs::File", "std::io"],
-                    Self::Network => vec!["std::net", "TcpStream", "UdpSocket"],
-                    Self::Threading => vec!["std::thread"],
-                    Self::FFI => vec!["extern", "libc", "std::os::unix::process::Command"],
-                    Self::Command => vec!["std::process::Command"],
-                    Self::Reflection => vec!["reflect"],
-                    Self::All => {
-                        vec![
-                            "unsafe",
-                            "std::fs::File",
-                            "std::io",
-                            "std::net",
-                            "TcpStream",
-                            "UdpSocket",
-                            "std::thread",
-                            "extern",
-                            "libc",
-                            "std::os::unix::process::Command",
-                            "std::process::Command",
-                            "reflection",
-                        ]
-                    }
-                    Self::SysAccess
-                    | Self::Runtime
-                    | Self::ProcessExec
-                    | Self::SystemCall
-                    | Self::Assembly
-                    | Self::Signal
-                    | Self::Process
-                    | Self::OsAccess
-                    | Self::Eval
-                    | Self::Exec
-                    | Self::Import
-                    | Self::Ctypes
-                    | Self::Pickle
-                    | Self::Unknown => vec![],
-                },
-                Language::Python => match &self {
-                    Self::OsAccess => vec!["os.system", "os.popen"],
-                    Self::Eval => vec!["eval("],
-                    Self::Exec => vec!["exec("],
-                    Self::FileIO => vec!["open("],
-                    Self::Threading => vec!["threading.Thread"],
-                    Self::Network => vec!["socket", "requests.get", "urllib", "subprocess"],
-                    Self::Import => vec!["__import__"],
-                    Self::Ctypes => vec!["ctypes"],
-                    Self::Pickle => vec!["pickle.loads", "pickle.dumps"],
-                    Self::All => {
-                        vec![
-                            "os.system",
-                            "os.popen",
-                            "eval(",
-                            "exec(",
-                            "open(",
-                            "threading.Thread",
-                            "socket",
-                            "requests.get",
-                            "urllib",
-                            "subprocess",
-                            "__import__",
-                            "ctypes",
-                            "pickle.loads",
-                            "pickle.dumps",
-                        ]
-                    }
-                    Self::SysAccess
-                    | Self::Runtime
-                    | Self::Reflection
-                    | Self::ProcessExec
-                    | Self::SystemCall
-                    | Self::Assembly
-                    | Self::Signal
-                    | Self::Process
-                    | Self::Unsafe
-                    | Self::FFI
-                    | Self::Command
-                    | Self::Unknown => vec![],
-                },
-                Language::Java => match &self {
-                    Self::FileIO => {
-                        vec![
-                            "java.io.FileInputStream",
-                            "java.io.FileOutputStream",
-                            "java.io.FileReader",
-                            "java.io.FileWriter",
-                        ]
-                    }
-                    Self::SysAccess => {
-                        vec![
-                            "System.exit",
-                            "System.setSecurityManager",
-                            "SecurityManager",
-                            "checkPermission",
-                        ]
-                    }
-                    Self::Runtime => {
-                        vec![
-                            "Runtime",
-                            "Runtime.exec",
-                            "Runtime.getRuntime",
-                            "runtimeexec",
-                        ]
-                    }
-                    Self::Threading => vec![
-                        "Thread",
-                        "Thread.start",
-                        "new Thread",
-                        "ExecutorService",
-                        "Executors.new",
-                        "ThreadPoolExecutor",
-                    ],
-                    Self::Reflection => {
-                        vec![
-                            "reflect",
-                            "Class.forName",
-                            "Class.getDeclaredMethod",
-                            "Class.getMethod",
-                            "setAccessible",
-                            "invoke",
-                        ]
-                    }
-                    Self::ProcessExec => vec!["ProcessBuilder", "Runtime.exec"],
-                    Self::All => {
-                        vec![
-                            "java.io.FileInputStream",
-                            "java.io.FileOutputStream",
-                            "java.io.FileReader",
-                            "java.io.FileWriter",
-                            "System.exit",
-                            "System.setSecurityManager",
-                            "SecurityManager",
-                            "checkPermission",
-                            "Runtime",
-                            "Runtime.exec",
-                            "Runtime.getRuntime",
-                            "runtimeexec",
-                            "Thread",
-                            "Thread.start",
-                            "reflect",
-                            "Class.forName",
-                            "Class.getDeclaredMethod",
-                            "Class.getMethod",
-                            "setAccessible",
-                            "invoke",
-                            "ProcessBuilder",
-                        ]
-                    }
-                    Self::SystemCall
-                    | Self::Network
-                    | Self::Assembly
-                    | Self::Signal
-                    | Self::Process
-                    | Self::Unsafe
-                    | Self::FFI
-                    | Self::Command
-                    | Self::OsAccess
-                    | Self::Eval
-                    | Self::Exec
-                    | Self::Import
-                    | Self::Ctypes
-                    | Self::Pickle
-                    | Self::Unknown => vec![],
-                },
-            }
-        }
     }
 }
@@ -0,0 +1,976 @@
+//! Java front end for `Type::AST`.
+//!
+//! This is not a full Java parser. It recovers the parts of a compilation unit the rules care
+//! about (imports, declared types, methods and variables, and expression chains such as
+//! `Runtime.getRuntime().exec(cmd)`), then resolves every name in a chain the way `javac`
+//! would before matching it against the prohibited constructs. Variables are not scoped; a
+//! name keeps the type of its last declaration in the file.
+
+use super::bytecode::looks_like_class;
+use super::lexer::{Pos, Token, TokenKind, tokenize_java};
+use super::rules::{self, Rule, Severity};
+use super::static_check::{self, Allow};
+use super::{IllegalExpr, source_line};
+use crate::executable::Language;
+use anyhow::Result;
+use core::fmt::{Display, Formatter};
+use log::debug;
+use std::collections::{HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// A construct a rule forbids, by fully-qualified name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// Any use of the type: imports, declarations, `new`, casts and static access.
+    Type(&'static str),
+    /// Any type in the package or its subpackages.
+    Package(&'static str),
+    /// A call of the method on the type, whether static or through an instance.
+    Method(&'static str, &'static str),
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Type(name) => write!(f, "{name}"),
+            Self::Package(pkg) => write!(f, "{pkg}.*"),
+            Self::Method(ty, name) => write!(f, "{ty}.{name}()"),
+        }
+    }
+}
+
+impl Pattern {
+    /// Reads a `resolved` name: `pkg.*` is a package and `Type.method()` a method.
+    pub fn parse(name: &'static str) -> Self {
+        if let Some(pkg) = name.strip_suffix(".*") {
+            return Self::Package(pkg);
+        }
+        match name
+            .strip_suffix("()")
+            .and_then(|call| call.rsplit_once('.'))
+        {
+            Some((ty, method)) => Self::Method(ty, method),
+            None => Self::Type(name),
+        }
+    }
+
+    pub fn matches_type(self, fq: &str) -> bool {
+        match self {
+            Self::Type(name) => name == fq,
+            Self::Package(pkg) => fq
+                .strip_prefix(pkg)
+                .is_some_and(|rest| rest.starts_with('.')),
+            Self::Method(..) => false,
+        }
+    }
+
+    pub fn matches_call(self, owner: &str, method: &str) -> bool {
+        matches!(self, Self::Method(ty, name) if ty == owner && name == method)
+    }
+}
+
+/// Active Java rules with a `resolved` name, with the construct each one names.
+pub fn resolved_rules(prohibited: &[Allow]) -> Vec<(&'static Rule, Pattern)> {
+    rules::resolved(&Language::Java, prohibited)
+        .into_iter()
+        .filter_map(|rule| Some((rule, Pattern::parse(rule.resolved.as_deref()?))))
+        .collect()
+}
+
+/// JDK types that wildcard imports and `java.lang` can bring into scope. Only types that can
+/// take part in a match need to be listed.
+static KNOWN_TYPES: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
+    let mut ret: HashSet<&'static str> = [
+        "java.lang.Class",
+        "java.lang.Object",
+        "java.lang.System",
+        "java.lang.reflect.Method",
+        "java.lang.reflect.Field",
+        "java.lang.reflect.Constructor",
+        "java.lang.reflect.Array",
+        "java.lang.reflect.Modifier",
+        "java.lang.reflect.Proxy",
+        "java.lang.reflect.InvocationHandler",
+        "java.lang.reflect.AccessibleObject",
+        "java.lang.invoke.MethodHandle",
+        "java.lang.invoke.MethodHandles",
+        "java.lang.invoke.MethodType",
+        "java.lang.invoke.VarHandle",
+    ]
+    .into();
+    for name in rules::resolved_names(&Language::Java) {
+        match Pattern::parse(name) {
+            Pattern::Type(name) | Pattern::Method(name, _) => {
+                ret.insert(name);
+            }
+            Pattern::Package(_) => {}
+        }
+    }
+    ret
+});
+
+/// Known return types, so chains like `Runtime.getRuntime().exec(..)` keep their type.
+fn returns(owner: Option<&str>, method: &str) -> Option<String> {
+    let ret = match (owner, method) {
+        (_, "getClass") => "java.lang.Class",
+        (Some("java.lang.Runtime"), "getRuntime") => "java.lang.Runtime",
+        (Some("java.lang.Class"), "forName") => "java.lang.Class",
+        (Some("java.lang.Class"), "getMethod" | "getDeclaredMethod") => "java.lang.reflect.Method",
+        (Some("java.lang.Class"), "getField" | "getDeclaredField") => "java.lang.reflect.Field",
+        (Some("java.lang.Class"), "getConstructor" | "getDeclaredConstructor") => {
+            "java.lang.reflect.Constructor"
+        }
+        (Some("java.lang.Thread"), "currentThread") => "java.lang.Thread",
+        (Some("java.lang.ProcessBuilder"), "start") | (Some("java.lang.Runtime"), "exec") => {
+            "java.lang.Process"
+        }
+        (Some("java.util.concurrent.Executors"), name) if name.starts_with("new") => {
+            "java.util.concurrent.ExecutorService"
+        }
+        _ => return None,
+    };
+    Some(ret.to_owned())
+}
+
+const KEYWORDS: [&str; 50] = [
+    "abstract",
+    "assert",
+    "boolean",
+    "break",
+    "byte",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "final",
+    "finally",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "instanceof",
+    "int",
+    "interface",
+    "long",
+    "native",
+    "new",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "static",
+    "strictfp",
+    "super",
+    "switch",
+    "synchronized",
+    "this",
+    "throw",
+    "throws",
+    "transient",
+    "try",
+    "void",
+    "volatile",
+    "while",
+];
+
+fn is_keyword(text: &str) -> bool {
+    KEYWORDS.contains(&text)
+}
+
+fn is_type_name(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_uppercase())
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub path: Vec<String>,
+    pub is_static: bool,
+    pub wildcard: bool,
+    pub start: Pos,
+    pub end: Pos,
+}
+
+/// One link of an expression chain.
+#[derive(Debug, Clone)]
+pub enum Segment {
+    Name(String),
+    Call(String),
+    New(Vec<String>),
+    ClassLiteral,
+    /// A string literal receiver, as in `"".getClass()`.
+    StringLiteral,
+}
+
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub segment: Segment,
+    /// End of the link, including the arguments of a call.
+    pub end: Pos,
+}
+
+/// A dotted expression such as `System.exit(1)` or `new Thread(r).start()`.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    pub start: Pos,
+    pub links: Vec<Link>,
+}
+
+#[derive(Debug, Default)]
+pub struct CompilationUnit {
+    pub package: Option<String>,
+    pub imports: Vec<Import>,
+    /// Types declared in this file; they shadow imported and `java.lang` names.
+    pub types: HashSet<String>,
+    pub methods: HashSet<String>,
+    /// Declared type of every variable, field and parameter.
+    pub variables: HashMap<String, Vec<String>>,
+    pub chains: Vec<Chain>,
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+}
+
+impl Parser<'_> {
+    fn get(&self, i: usize) -> Option<&Token> {
+        self.tokens.get(i)
+    }
+
+    fn is(&self, i: usize, text: &str) -> bool {
+        self.get(i).is_some_and(|t| t.is(text))
+    }
+
+    fn ident(&self, i: usize) -> Option<&str> {
+        self.get(i)
+            .filter(|t| t.is_ident())
+            .map(|t| t.text.as_str())
+    }
+
+    /// Index just past the bracket that closes the one at `i`.
+    fn skip_balanced(&self, i: usize, open: &str, close: &str) -> usize {
+        let mut depth = 0usize;
+        let mut j = i;
+        while let Some(t) = self.get(j) {
+            if t.is(open) {
+                depth += 1;
+            } else if t.is(close) {
+                depth -= 1;
+                if depth == 0 {
+                    return j + 1;
+                }
+            }
+            j += 1;
+        }
+        j
+    }
+
+    /// Index past a generic argument list at `i`, if it looks like one.
+    fn skip_generics(&self, i: usize) -> Option<usize> {
+        if !self.is(i, "<") {
+            return None;
+        }
+        let mut depth = 0usize;
+        let mut j = i;
+        while let Some(t) = self.get(j) {
+            match t.text.as_str() {
+                "<" => depth += 1,
+                ">" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(j + 1);
+                    }
+                }
+                "." | "," | "?" | "&" | "[" | "]" => {}
+                _ if t.is_ident() => {}
+                _ => return None,
+            }
+            j += 1;
+        }
+        None
+    }
+
+    /// Reads `a.b.C` starting at `i`.
+    fn dotted(&self, mut i: usize) -> (Vec<String>, usize) {
+        let mut ret = vec![];
+        while let Some(name) = self.ident(i) {
+            ret.push(name.to_owned());
+            if self.is(i + 1, ".") && self.ident(i + 2).is_some() {
+                i += 2;
+            } else {
+                i += 1;
+                break;
+            }
+        }
+        (ret, i)
+    }
+
+    fn import(&self, i: usize, unit: &mut CompilationUnit) -> usize {
+        let start = self.tokens[i].start;
+        let mut j = i + 1;
+        let is_static = self.is(j, "static");
+        if is_static {
+            j += 1;
+        }
+        let (path, next) = self.dotted(j);
+        j = next;
+        let wildcard = self.is(j, ".") && self.is(j + 1, "*");
+        if wildcard {
+            j += 2;
+        }
+        let end = self.get(j).map_or(start, |t| t.end);
+        unit.imports.push(Import {
+            path,
+            is_static,
+            wildcard,
+            start,
+            end,
+        });
+        j + 1
+    }
+
+    /// Records a declaration when the type ending before `i` is followed by a name.
+    fn declaration(&self, ty: &[String], mut i: usize, unit: &mut CompilationUnit) {
+        if let Some(next) = self.skip_generics(i) {
+            i = next;
+        }
+        while self.is(i, "[") && self.is(i + 1, "]") {
+            i += 2;
+        }
+        if self.is(i, "...") || (self.is(i, ".") && self.is(i + 1, ".") && self.is(i + 2, ".")) {
+            i += 3;
+        }
+        let Some(name) = self.ident(i).filter(|name| !is_keyword(name)) else {
+            return;
+        };
+        if self.is(i + 1, "(") {
+            unit.methods.insert(name.to_owned());
+        } else if self
+            .get(i + 1)
+            .is_some_and(|t| ["=", ";", ",", ")", ":"].contains(&t.text.as_str()))
+        {
+            unit.variables.insert(name.to_owned(), ty.to_vec());
+        }
+    }
+
+    fn chain(&self, mut i: usize) -> (Chain, usize) {
+        let start = self.tokens[i].start;
+        let mut links = vec![];
+        loop {
+            if self.is(i, "new") {
+                let (ty, next) = self.dotted(i + 1);
+                i = self.skip_generics(next).unwrap_or(next);
+                let mut end = self.tokens[i.saturating_sub(1)].end;
+                if self.is(i, "(") {
+                    i = self.skip_balanced(i, "(", ")");
+                    end = self.tokens[i - 1].end;
+                }
+                links.push(Link {
+                    segment: Segment::New(ty),
+                    end,
+                });
+            } else if links.is_empty() && self.get(i).is_some_and(|t| t.kind == TokenKind::Str) {
+                links.push(Link {
+                    segment: Segment::StringLiteral,
+                    end: self.tokens[i].end,
+                });
+                i += 1;
+            } else if self.is(i, "class") && !links.is_empty() {
+                links.push(Link {
+                    segment: Segment::ClassLiteral,
+                    end: self.tokens[i].end,
+                });
+                i += 1;
+            } else if let Some(name) = self.ident(i) {
+                if self.is(i + 1, "(") {
+                    i = self.skip_balanced(i + 1, "(", ")");
+                    links.push(Link {
+                        segment: Segment::Call(name.to_owned()),
+                        end: self.tokens[i - 1].end,
+                    });
+                } else {
+                    links.push(Link {
+                        segment: Segment::Name(name.to_owned()),
+                        end: self.tokens[i].end,
+                    });
+                    i += 1;
+                }
+            } else {
+                break;
+            }
+            if !self.is(i, ".") {
+                break;
+            }
+            i += 1;
+            // explicit type arguments: `Collections.<T>emptyList()`.
+            if let Some(next) = self.skip_generics(i) {
+                i = next;
+            }
+        }
+        (Chain { start, links }, i)
+    }
+
+    fn parse(&self) -> CompilationUnit {
+        let mut unit = CompilationUnit::default();
+        let mut i = 0;
+        while let Some(t) = self.get(i) {
+            let prev = i.checked_sub(1).and_then(|p| self.get(p));
+            let after_dot = prev.is_some_and(|p| p.is("."));
+            match t.text.as_str() {
+                "package" if t.is_ident() => {
+                    let (path, next) = self.dotted(i + 1);
+                    unit.package = Some(path.join("."));
+                    i = next;
+                    continue;
+                }
+                "import" if t.is_ident() => {
+                    i = self.import(i, &mut unit);
+                    continue;
+                }
+                "class" | "interface" | "enum" | "record" if !after_dot => {
+                    if let Some(name) = self.ident(i + 1) {
+                        unit.types.insert(name.to_owned());
+                    }
+                    i += 2;
+                    continue;
+                }
+                "@" => {
+                    // annotations are not expressions.
+                    let (_, next) = self.dotted(i + 1);
+                    i = next;
+                    continue;
+                }
+                _ => {}
+            }
+            let starts_chain = (t.is_ident() && (!is_keyword(&t.text) || t.is("new")))
+                || t.is("this")
+                || t.is("super")
+                || (t.kind == TokenKind::Str && self.is(i + 1, "."));
+            if starts_chain && !after_dot && !prev.is_some_and(|p| p.is("new")) {
+                let (chain, next) = self.chain(i);
+                if let [
+                    Link {
+                        segment: Segment::Name(_),
+                        ..
+                    },
+                    ..,
+                ] = chain.links.as_slice()
+                    && chain
+                        .links
+                        .iter()
+                        .all(|link| matches!(link.segment, Segment::Name(_)))
+                {
+                    let ty: Vec<String> = chain
+                        .links
+                        .iter()
+                        .filter_map(|link| match &link.segment {
+                            Segment::Name(name) => Some(name.clone()),
+                            _ => None,
+                        })
+                        .collect();
+                    self.declaration(&ty, next, &mut unit);
+                }
+                unit.chains.push(chain);
+            } else if t.kind == TokenKind::Ident
+                && !is_keyword(&t.text)
+                && self.is(i + 1, "(")
+                && prev.is_some_and(|p| p.is_ident() && !is_keyword(&p.text))
+            {
+                unit.methods.insert(t.text.clone());
+            }
+            i += 1;
+        }
+        unit
+    }
+}
+
+pub fn parse(tokens: &[Token]) -> CompilationUnit {
+    Parser { tokens }.parse()
+}
+
+/// A prohibited construct found in a compilation unit.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub rule: &'static Rule,
+    pub pattern: Pattern,
+    pub start: Pos,
+    pub end: Pos,
+}
+
+struct Resolver<'a> {
+    unit: &'a CompilationUnit,
+    rules: &'a [(&'static Rule, Pattern)],
+    hits: Vec<Hit>,
+}
+
+impl Resolver<'_> {
+    fn simple_type(&self, name: &str) -> Option<String> {
+        if self.unit.types.contains(name) {
+            return Some(match &self.unit.package {
+                Some(pkg) => format!("{pkg}.{name}"),
+                None => name.to_owned(),
+            });
+        }
+        let imported = self.unit.imports.iter().find(|import| {
+            !import.is_static && !import.wildcard && import.path.last().is_some_and(|n| n == name)
+        });
+        if let Some(import) = imported {
+            return Some(import.path.join("."));
+        }
+        let lang = format!("java.lang.{name}");
+        if KNOWN_TYPES.contains(lang.as_str()) {
+            return Some(lang);
+        }
+        self.unit
+            .imports
+            .iter()
+            .filter(|import| !import.is_static && import.wildcard)
+            .map(|import| format!("{}.{name}", import.path.join(".")))
+            .find(|fq| KNOWN_TYPES.contains(fq.as_str()))
+    }
+
+    fn type_of(&self, path: &[String]) -> Option<String> {
+        let (first, rest) = path.split_first()?;
+        if is_type_name(first) {
+            let base = self.simple_type(first)?;
+            Some(rest.iter().fold(base, |acc, part| format!("{acc}.{part}")))
+        } else {
+            Some(path.join("."))
+        }
+    }
+
+    fn hit_type(&mut self, fq: &str, start: Pos, end: Pos) {
+        for (rule, pattern) in self.rules {
+            if pattern.matches_type(fq) {
+                self.hits.push(Hit {
+                    rule,
+                    pattern: *pattern,
+                    start,
+                    end,
+                });
+            }
+        }
+    }
+
+    fn hit_call(&mut self, owner: &str, method: &str, start: Pos, end: Pos) {
+        for (rule, pattern) in self.rules {
+            if pattern.matches_call(owner, method) {
+                self.hits.push(Hit {
+                    rule,
+                    pattern: *pattern,
+                    start,
+                    end,
+                });
+            }
+        }
+    }
+
+    /// Owner of an unqualified call that is not declared in this file.
+    fn static_owner(&self, method: &str) -> Option<String> {
+        let imports = self.unit.imports.iter().filter(|import| import.is_static);
+        let mut wildcard = None;
+        for import in imports {
+            if import.wildcard {
+                let owner = import.path.join(".");
+                let known = self
+                    .rules
+                    .iter()
+                    .any(|(_, p)| p.matches_call(&owner, method));
+                if known {
+                    wildcard = Some(owner);
+                }
+            } else if let Some((name, owner)) = import.path.split_last()
+                && name == method
+            {
+                return Some(owner.join("."));
+            }
+        }
+        wildcard
+    }
+
+    fn import(&mut self, import: &Import) {
+        let fq = import.path.join(".");
+        if import.is_static {
+            if let Some((method, owner)) = import.path.split_last() {
+                let owner = owner.join(".");
+                self.hit_type(&owner, import.start, import.end);
+                if !import.wildcard {
+                    self.hit_call(&owner, method, import.start, import.end);
+                }
+            }
+        } else if import.wildcard {
+            // importing a package only counts when the whole package is prohibited.
+            let probe = format!("{fq}.*");
+            for (rule, pattern) in self.rules {
+                if matches!(pattern, Pattern::Package(_)) && pattern.matches_type(&probe) {
+                    self.hits.push(Hit {
+                        rule,
+                        pattern: *pattern,
+                        start: import.start,
+                        end: import.end,
+                    });
+                }
+            }
+        } else {
+            self.hit_type(&fq, import.start, import.end);
+        }
+    }
+
+    fn chain(&mut self, chain: &Chain) {
+        let mut ty: Option<String> = None;
+        let mut package: Vec<String> = vec![];
+        for (k, link) in chain.links.iter().enumerate() {
+            let end = link.end;
+            match &link.segment {
+                Segment::Name(name) if k == 0 => {
+                    if let Some(declared) = self.unit.variables.get(name) {
+                        ty = self.type_of(declared);
+                    } else if is_type_name(name)
+                        && let Some(fq) = self.simple_type(name)
+                    {
+                        self.hit_type(&fq, chain.start, end);
+                        ty = Some(fq);
+                    } else if !is_type_name(name) {
+                        package.push(name.clone());
+                    }
+                }
+                Segment::Name(name) => {
+                    if !package.is_empty() {
+                        if is_type_name(name) {
+                            let fq = format!("{}.{name}", package.join("."));
+                            package.clear();
+                            self.hit_type(&fq, chain.start, end);
+                            ty = Some(fq);
+                        } else {
+                            package.push(name.clone());
+                        }
+                    } else if let Some(owner) = ty.take()
+                        && is_type_name(name)
+                    {
+                        // a nested type.
+                        let fq = format!("{owner}.{name}");
+                        self.hit_type(&fq, chain.start, end);
+                        ty = Some(fq);
+                    }
+                }
+                Segment::Call(method) => {
+                    let owner = if k == 0 {
+                        if self.unit.methods.contains(method) {
+                            None
+                        } else {
+                            self.static_owner(method)
+                        }
+                    } else {
+                        ty.take()
+                    };
+                    if let Some(owner) = &owner {
+                        self.hit_call(owner, method, chain.start, end);
+                    }
+                    ty = returns(owner.as_deref(), method);
+                    package.clear();
+                }
+                Segment::New(name) => {
+                    ty = self.type_of(name);
+                    if let Some(fq) = &ty {
+                        self.hit_type(fq, chain.start, end);
+                    }
+                }
+                Segment::ClassLiteral => {
+                    ty = Some("java.lang.Class".to_owned());
+                    package.clear();
+                }
+                Segment::StringLiteral => ty = Some("java.lang.String".to_owned()),
+            }
+        }
+    }
+}
+
+/// Matches a parsed unit against the resolved rules of the given categories.
+pub fn find(unit: &CompilationUnit, prohibited: &[Allow]) -> Vec<Hit> {
+    let rules = resolved_rules(prohibited);
+    let mut resolver = Resolver {
+        unit,
+        rules: &rules,
+        hits: vec![],
+    };
+    for import in &unit.imports {
+        resolver.import(import);
+    }
+    for chain in &unit.chains {
+        resolver.chain(chain);
+    }
+    let mut hits = resolver.hits;
+    // one finding per category for each construct, from the first rule that names it; rules
+    // without a category each get their own.
+    let key = |hit: &Hit| {
+        let category = hit.rule.category.as_ref().map(AsRef::<str>::as_ref);
+        let name = category.is_none().then_some(hit.rule.name.as_str());
+        (hit.start, hit.end, category, name)
+    };
+    hits.sort_by(|a, b| key(a).cmp(&key(b)));
+    hits.dedup_by(|a, b| key(a) == key(b));
+    hits
+}
+
+/// Whether `allowed` permits the type `fq`. Entries are classes (which include their nested
+/// types), `pkg.*` for the types directly in a package, or `pkg.**` for its subpackages too.
+fn permits_type(allowed: &[String], fq: &str) -> bool {
+    let package: Vec<&str> = fq
+        .split('.')
+        .take_while(|part| !is_type_name(part))
+        .collect();
+    let package = package.join(".");
+    allowed.iter().any(|entry| {
+        if let Some(prefix) = entry.strip_suffix(".**") {
+            package == prefix || package.starts_with(&format!("{prefix}."))
+        } else if let Some(prefix) = entry.strip_suffix(".*") {
+            package == prefix
+        } else {
+            fq == entry || fq.starts_with(&format!("{entry}."))
+        }
+    })
+}
+
+/// Whether `allowed` permits every type a wildcard import of `package` can bring in.
+fn permits_package(allowed: &[String], package: &str) -> bool {
+    allowed.iter().any(|entry| {
+        if let Some(prefix) = entry.strip_suffix(".**") {
+            package == prefix || package.starts_with(&format!("{prefix}."))
+        } else {
+            entry.strip_suffix(".*") == Some(package)
+        }
+    })
+}
+
+/// Imports and fully-qualified names in `unit` that `allowed` does not permit, with the name
+/// that was rejected. `java.lang` types used by their simple names are left to the categories.
+pub fn outside_allowlist(unit: &CompilationUnit, allowed: &[String]) -> Vec<(String, Pos, Pos)> {
+    let mut ret = vec![];
+    for import in &unit.imports {
+        let fq = import.path.join(".");
+        let permitted = if import.is_static {
+            // the last part is a member unless the whole type is imported with `.*`.
+            let owner = if import.wildcard {
+                fq.clone()
+            } else {
+                import.path[..import.path.len().saturating_sub(1)].join(".")
+            };
+            permits_type(allowed, &owner)
+        } else if import.wildcard {
+            permits_package(allowed, &fq)
+        } else {
+            permits_type(allowed, &fq)
+        };
+        if !permitted {
+            let shown = if import.wildcard {
+                format!("{fq}.*")
+            } else {
+                fq
+            };
+            ret.push((shown, import.start, import.end));
+        }
+    }
+    for chain in &unit.chains {
+        let mut package = vec![];
+        for link in &chain.links {
+            let name = match &link.segment {
+                Segment::Name(name) => name,
+                Segment::New(path) => {
+                    if path.first().is_some_and(|first| !is_type_name(first)) {
+                        let fq = path.join(".");
+                        if !permits_type(allowed, &fq) {
+                            ret.push((fq, chain.start, link.end));
+                        }
+                    }
+                    break;
+                }
+                Segment::Call(_) | Segment::ClassLiteral | Segment::StringLiteral => break,
+            };
+            if package.is_empty()
+                && (is_type_name(name) || unit.variables.contains_key(name) || is_keyword(name))
+            {
+                break;
+            }
+            if is_type_name(name) {
+                let fq = format!("{}.{name}", package.join("."));
+                if !permits_type(allowed, &fq) {
+                    ret.push((fq, chain.start, link.end));
+                }
+                break;
+            }
+            package.push(name.clone());
+        }
+    }
+    ret
+}
+
+/// Findings for everything in a Java source that the `imports` allowlist does not permit.
+pub fn check_imports(path: &Path, src: &str, allowed: &[String]) -> Vec<IllegalExpr> {
+    let unit = parse(&tokenize_java(src));
+    outside_allowlist(&unit, allowed)
+        .into_iter()
+        .map(|(name, start, end)| IllegalExpr {
+            content: Some(source_line(src, start.0)),
+            rule: Some("imports".to_owned()),
+            message: Some(format!("`{name}` is not in the import allowlist")),
+            severity: Severity::Error,
+            action: rules::category_action(&Allow::Import),
+            violates: Some(Allow::Import),
+            loc: (start.1, start.0),
+            end: (end.1, end.0),
+            path: path.to_path_buf(),
+        })
+        .collect()
+}
+
+/// Value of a plain string literal, without its quotes.
+fn unquote(token: &Token) -> Option<&str> {
+    if token.kind != TokenKind::Str || token.text.starts_with("\"\"\"") {
+        return None;
+    }
+    token.text.strip_prefix('"')?.strip_suffix('"')
+}
+
+/// Spellings that hide what the code refers to: code written with Unicode escapes, names of
+/// classes `prohibited` forbids assembled from string literals, and classes loaded by a name
+/// computed at run time.
+pub fn obfuscation(tokens: &[Token], prohibited: &[Allow]) -> Vec<(String, Pos, Pos)> {
+    let mut ret = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if token.escaped && !matches!(token.kind, TokenKind::Str | TokenKind::Char) {
+            ret.push((
+                format!("`{}` is written with Unicode escapes", token.text),
+                token.start,
+                token.end,
+            ));
+        }
+        // `"java.lang." + "Runtime"`: literals `javac` folds into one class name. `"www." +
+        // "example"` is joined the same way, so only prohibited classes count.
+        if let Some(first) = unquote(token) {
+            let mut name = first.to_owned();
+            let mut j = i;
+            while tokens.get(j + 1).is_some_and(|t| t.is("+"))
+                && let Some(next) = tokens.get(j + 2).and_then(unquote)
+            {
+                name.push_str(next);
+                j += 2;
+            }
+            if j > i && looks_like_class(&name) && prohibits_type(prohibited, &name) {
+                ret.push((
+                    format!("class name `{name}` is built from strings"),
+                    token.start,
+                    tokens[j].end,
+                ));
+            }
+            i = j + 1;
+            continue;
+        }
+        let loads =
+            (token.is("forName") && i >= 2 && tokens[i - 2].is("Class")) || token.is("loadClass");
+        if loads && i >= 1 && tokens[i - 1].is(".") && tokens.get(i + 1).is_some_and(|t| t.is("("))
+        {
+            let literal = tokens.get(i + 2).is_some_and(|t| unquote(t).is_some())
+                && tokens.get(i + 3).is_some_and(|t| t.is(")") || t.is(","));
+            if !literal {
+                ret.push((
+                    format!("`{}` loads a class by a computed name", token.text),
+                    token.start,
+                    token.end,
+                ));
+            }
+        }
+        i += 1;
+    }
+    ret
+}
+
+/// Whether an active resolved rule forbids the type `fq`.
+fn prohibits_type(prohibited: &[Allow], fq: &str) -> bool {
+    resolved_rules(prohibited)
+        .iter()
+        .any(|(_, pattern)| pattern.matches_type(fq))
+}
+
+/// Findings for the obfuscation in a Java source.
+pub fn check_obfuscation(path: &Path, src: &str, prohibited: &[Allow]) -> Vec<IllegalExpr> {
+    obfuscation(&tokenize_java(src), prohibited)
+        .into_iter()
+        .map(|(message, start, end)| IllegalExpr {
+            content: Some(source_line(src, start.0)),
+            rule: Some("obfuscation".to_owned()),
+            message: Some(message),
+            severity: Severity::Error,
+            action: rules::category_action(&Allow::Obfuscation),
+            violates: Some(Allow::Obfuscation),
+            loc: (start.1, start.0),
+            end: (end.1, end.0),
+            path: path.to_path_buf(),
+        })
+        .collect()
+}
+
+pub fn check(path: &PathBuf) -> Result<Vec<IllegalExpr>> {
+    let lang: Language = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .into();
+    if lang == Language::Python {
+        return super::python::check(path);
+    }
+    if matches!(lang, Language::C | Language::Cpp) {
+        return super::c::check(path);
+    }
+    if lang != Language::Java {
+        debug!(
+            "No AST support for {}; using static analysis.",
+            path.display()
+        );
+        return static_check::check(path);
+    }
+    let prohibited = static_check::prohibited()?;
+    let src = read_to_string(path)?;
+    let mut ret = check_source(path, &src, &prohibited);
+    let unresolved = rules::unresolved(&lang, &prohibited);
+    ret.extend(static_check::check_source(path, &src, &lang, &unresolved));
+    ret.sort_by_key(|expr| (expr.loc.1, expr.loc.0));
+    Ok(ret)
+}
+
+pub fn check_source(path: &Path, src: &str, prohibited: &[Allow]) -> Vec<IllegalExpr> {
+    let tokens = tokenize_java(src);
+    let unit = parse(&tokens);
+    find(&unit, prohibited)
+        .into_iter()
+        .map(|hit| IllegalExpr {
+            content: Some(source_line(src, hit.start.0)),
+            rule: Some(hit.rule.name.clone()),
+            message: hit
+                .rule
+                .message
+                .clone()
+                .or_else(|| Some(format!("resolves to `{}`", hit.pattern))),
+            severity: hit.rule.severity,
+            action: hit.rule.action(),
+            violates: hit.rule.category.clone(),
+            loc: (hit.start.1, hit.start.0),
+            end: (hit.end.1, hit.end.0),
+            path: path.to_path_buf(),
+        })
+        .collect()
+}
@@ -0,0 +1,282 @@
+//! Scans the constant pools of compiled `.class` files for prohibited classes and methods.
+//!
+//! Source checks can be dodged with tricks the compiler sees through, such as Unicode escapes
+//! or `Class.forName("java.lang." + "Runtime")`, whose argument `javac` folds into one constant.
+//! The constant pool lists every class, method and field a class refers to, and every string
+//! constant, so those references show up here no matter how the source spelled them.
+
+use std::collections::HashSet;
+use std::fs::read;
+use std::path::Path;
+
+use anyhow::{Result, bail};
+use log::{debug, error};
+use walkdir::WalkDir;
+
+use super::IllegalExpr;
+use super::ast::{Pattern, resolved_rules};
+use super::rules::Rule;
+use super::static_check::{self, Allow};
+
+/// Something a class file refers to, with names in source form (`java.lang.Runtime`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reference {
+    Class(String),
+    /// A method or field of `owner`.
+    Member {
+        owner: String,
+        name: String,
+    },
+    /// A string constant that looks like a class name.
+    ClassName(String),
+}
+
+impl core::fmt::Display for Reference {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Class(name) => write!(f, "{name}"),
+            Self::Member { owner, name } => write!(f, "{owner}.{name}"),
+            Self::ClassName(name) => write!(f, "\"{name}\""),
+        }
+    }
+}
+
+enum Constant {
+    Utf8(String),
+    Class(u16),
+    Str(u16),
+    /// Field, method and interface method references.
+    Member(u16, u16),
+    NameAndType(u16),
+    Other,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8]> {
+        let Some(slice) = self.bytes.get(self.pos..self.pos + n) else {
+            bail!("truncated class file");
+        };
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u1(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u2(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+}
+
+/// Source form of an internal class name or array descriptor, e.g. `[Ljava/lang/Runtime;`.
+fn class_name(internal: &str) -> String {
+    let name = internal.trim_start_matches('[');
+    let name = name
+        .strip_prefix('L')
+        .and_then(|rest| rest.strip_suffix(';'))
+        .unwrap_or(name);
+    name.replace(['/', '$'], ".")
+}
+
+/// Whether a string constant could name a class, as in `Class.forName("java.lang.Runtime")`.
+pub fn looks_like_class(s: &str) -> bool {
+    s.contains('.')
+        && s.split('.').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        })
+}
+
+/// Every class, member and class-like string the class file refers to.
+pub fn references(bytes: &[u8]) -> Result<Vec<Reference>> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(4)? != [0xCA, 0xFE, 0xBA, 0xBE] {
+        bail!("not a class file");
+    }
+    reader.take(4)?;
+    let count = reader.u2()?;
+    // index 0 is unused; longs and doubles take two slots.
+    let mut pool: Vec<Constant> = vec![Constant::Other];
+    while pool.len() < usize::from(count) {
+        let constant = match reader.u1()? {
+            1 => {
+                let len = reader.u2()?;
+                Constant::Utf8(String::from_utf8_lossy(reader.take(len.into())?).into_owned())
+            }
+            7 => Constant::Class(reader.u2()?),
+            8 => Constant::Str(reader.u2()?),
+            9..=11 => {
+                let class = reader.u2()?;
+                Constant::Member(class, reader.u2()?)
+            }
+            12 => {
+                let name = reader.u2()?;
+                reader.u2()?;
+                Constant::NameAndType(name)
+            }
+            3 | 4 => {
+                reader.take(4)?;
+                Constant::Other
+            }
+            5 | 6 => {
+                reader.take(8)?;
+                pool.push(Constant::Other);
+                Constant::Other
+            }
+            15 => {
+                reader.take(3)?;
+                Constant::Other
+            }
+            16 | 19 | 20 => {
+                reader.take(2)?;
+                Constant::Other
+            }
+            17 | 18 => {
+                reader.take(4)?;
+                Constant::Other
+            }
+            tag => bail!("unknown constant pool tag {tag}"),
+        };
+        pool.push(constant);
+    }
+    let utf8 = |idx: u16| match pool.get(usize::from(idx)) {
+        Some(Constant::Utf8(s)) => Some(s.as_str()),
+        _ => None,
+    };
+    let class = |idx: u16| match pool.get(usize::from(idx)) {
+        Some(Constant::Class(name)) => utf8(*name).map(class_name),
+        _ => None,
+    };
+    let mut ret = vec![];
+    for constant in &pool {
+        match constant {
+            Constant::Class(name) => {
+                if let Some(name) = utf8(*name) {
+                    ret.push(Reference::Class(class_name(name)));
+                }
+            }
+            Constant::Member(owner, nat) => {
+                let name = match pool.get(usize::from(*nat)) {
+                    Some(Constant::NameAndType(name)) => utf8(*name),
+                    _ => None,
+                };
+                if let (Some(owner), Some(name)) = (class(*owner), name) {
+                    ret.push(Reference::Member {
+                        owner,
+                        name: name.to_owned(),
+                    });
+                }
+            }
+            Constant::Str(value) => {
+                if let Some(value) = utf8(*value).map(|s| s.replace('/', "."))
+                    && looks_like_class(&value)
+                {
+                    ret.push(Reference::ClassName(value));
+                }
+            }
+            Constant::Utf8(_) | Constant::NameAndType(_) | Constant::Other => {}
+        }
+    }
+    Ok(ret)
+}
+
+/// Bootstrap methods `javac` itself emits for string concatenation, lambdas and records.
+const BOOTSTRAPS: [&str; 3] = [
+    "java.lang.invoke.StringConcatFactory",
+    "java.lang.invoke.LambdaMetafactory",
+    "java.lang.runtime.ObjectMethods",
+];
+
+/// Classes every class with a bootstrap method lists as an inner class. Calls on them are
+/// still member references.
+const LOOKUP: [&str; 2] = [
+    "java.lang.invoke.MethodHandles",
+    "java.lang.invoke.MethodHandles.Lookup",
+];
+
+fn generated(reference: &Reference) -> bool {
+    match reference {
+        Reference::Class(name) => {
+            BOOTSTRAPS.contains(&name.as_str()) || LOOKUP.contains(&name.as_str())
+        }
+        Reference::Member { owner, .. } => BOOTSTRAPS.contains(&owner.as_str()),
+        Reference::ClassName(_) => false,
+    }
+}
+
+/// The first of `rules` that names a reference, if any.
+fn matching(
+    reference: &Reference,
+    rules: &[(&'static Rule, Pattern)],
+) -> Option<(&'static Rule, Pattern)> {
+    if generated(reference) {
+        return None;
+    }
+    rules
+        .iter()
+        .find(|(_, pattern)| match reference {
+            Reference::Class(fq) | Reference::ClassName(fq) => pattern.matches_type(fq),
+            Reference::Member { owner, name } => {
+                pattern.matches_call(owner, name) || pattern.matches_type(owner)
+            }
+        })
+        .copied()
+}
+
+/// Findings for one class file, one per distinct reference.
+pub fn check_class(path: &Path, bytes: &[u8], prohibited: &[Allow]) -> Result<Vec<IllegalExpr>> {
+    let rules = resolved_rules(prohibited);
+    let mut seen = HashSet::new();
+    let mut ret = vec![];
+    for reference in references(bytes)? {
+        let Some((rule, pattern)) = matching(&reference, &rules) else {
+            continue;
+        };
+        let shown = reference.to_string();
+        if !seen.insert(shown.clone()) {
+            continue;
+        }
+        ret.push(IllegalExpr {
+            content: Some(shown),
+            rule: Some(rule.name.clone()),
+            message: rule
+                .message
+                .clone()
+                .or_else(|| Some(format!("resolves to `{pattern}`"))),
+            severity: rule.severity,
+            action: rule.action(),
+            violates: rule.category.clone(),
+            loc: (0, 0),
+            end: (0, 0),
+            path: path.to_path_buf(),
+        });
+    }
+    Ok(ret)
+}
+
+/// Scans every `.class` file under `dir`.
+pub fn check_dir(dir: &Path) -> Result<Vec<IllegalExpr>> {
+    let prohibited = static_check::prohibited()?;
+    let mut ret = vec![];
+    for entry in WalkDir::new(dir).into_iter().filter_map(Result::ok) {
+        let path = entry.path();
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != "class") {
+            continue;
+        }
+        match check_class(path, &read(path)?, &prohibited) {
+            Ok(found) => ret.extend(found),
+            Err(e) => error!("Failed to scan {}: {e}", path.display()),
+        }
+    }
+    debug!("{}: {} bytecode finding(s).", dir.display(), ret.len());
+    Ok(ret)
+}
@@ -0,0 +1,419 @@
+//! C and C++ front end for `Type::AST`.
+//!
+//! Rules whose pattern is a single name are matched on identifiers that can refer to the
+//! library: `obj.send` and `p->wait` are members, and functions the file defines itself are its
+//! own. Macros are not: `#define system(x) system(x)` would otherwise hide every call, so their
+//! names are matched like any other and their bodies are checked too. A `__builtin_` call counts
+//! as the function it wraps, every spelling of `asm` counts as `asm`, and names a macro pastes
+//! together with `##` are matched as well.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::lexer::{Pos, Syntax, Token, TokenKind, tokenize};
+use super::require::brace_methods;
+use super::rules::{self, Rule, Severity};
+use super::static_check::{self, Allow};
+use super::{IllegalExpr, source_line};
+use crate::executable::Language;
+
+/// The name a rule sees: `__builtin_exit` is `exit`, and `__asm__`, `__asm` and `_asm` are `asm`.
+fn canonical(name: &str) -> &str {
+    if ["__asm__", "__asm", "_asm"].contains(&name) {
+        return "asm";
+    }
+    name.strip_prefix("__builtin_")
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(name)
+}
+
+/// Words after which a name is not being declared.
+const NOT_TYPES: [&str; 10] = [
+    "return",
+    "case",
+    "goto",
+    "else",
+    "do",
+    "sizeof",
+    "new",
+    "delete",
+    "throw",
+    "co_return",
+];
+
+/// Preprocessor directives: the tokens from `#` to the end of the line and any continuation
+/// lines.
+fn directives(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut ret = vec![];
+    let mut i = 0;
+    while i < tokens.len() {
+        if !tokens[i].is("#") || (i > 0 && tokens[i - 1].end.0 == tokens[i].start.0) {
+            i += 1;
+            continue;
+        }
+        let mut line = tokens[i].start.0;
+        let mut j = i + 1;
+        while j < tokens.len() && tokens[j].start.0 <= line {
+            if tokens[j].is("\\")
+                && let Some(next) = tokens.get(j + 1)
+            {
+                line = next.start.0;
+            }
+            j += 1;
+        }
+        ret.push(&tokens[i..j]);
+        i = j;
+    }
+    ret
+}
+
+/// Names the file defines itself. Functions take over calls; variables, parameters and fields
+/// only take over other uses, so a variable named `send` does not hide `send(...)`. Macros never
+/// take over a name, since one can expand to the very function it is named after, and
+/// directives are left out so `#define system(x) ...` before a `{` is not taken for a function.
+#[derive(Debug, Default)]
+struct Locals {
+    functions: HashSet<String>,
+    variables: HashSet<String>,
+}
+
+fn locals(tokens: &[Token], directives: &[&[Token]]) -> Locals {
+    let mut ret = Locals::default();
+    let in_directives: HashSet<Pos> = directives
+        .iter()
+        .flat_map(|d| d.iter().map(|t| t.start))
+        .collect();
+    let code: Vec<Token> = tokens
+        .iter()
+        .filter(|t| !in_directives.contains(&t.start))
+        .cloned()
+        .collect();
+    for method in brace_methods(&code) {
+        ret.functions.insert(method.name.to_owned());
+    }
+    for i in 1..tokens.len() {
+        let (prev, name) = (&tokens[i - 1], &tokens[i]);
+        if !name.is_ident()
+            || !tokens
+                .get(i + 1)
+                .is_some_and(|next| ["=", ";", ",", "[", ")"].iter().any(|p| next.is(p)))
+        {
+            continue;
+        }
+        let declares = (prev.is_ident() && !NOT_TYPES.contains(&prev.text.as_str()))
+            || ((prev.is("*") || prev.is("&"))
+                && i >= 2
+                && (tokens[i - 2].is_ident() || tokens[i - 2].is("*")));
+        if declares {
+            ret.variables.insert(name.text.clone());
+        }
+    }
+    ret
+}
+
+/// Whether the identifier at `i` can name a library function rather than a member, a
+/// qualified name or a struct tag.
+fn is_free(tokens: &[Token], i: usize) -> bool {
+    let back = |n: usize| i.checked_sub(n).map(|j| &tokens[j]);
+    match back(1) {
+        Some(prev) if prev.is(".") => false,
+        Some(prev) if prev.is(">") => !back(2).is_some_and(|t| t.is("-")),
+        // `std::system` and `::system` are the library; `Client::send` is not.
+        Some(prev) if prev.is(":") && back(2).is_some_and(|t| t.is(":")) => {
+            back(3).is_none_or(|owner| owner.is("std") || !owner.is_ident())
+        }
+        Some(prev) => !["struct", "union", "enum", "class"].contains(&prev.text.as_str()),
+        None => true,
+    }
+}
+
+/// The single name a rule matches, if its pattern is one.
+fn single_name(rule: &Rule) -> Option<String> {
+    if rule.regex().is_some() {
+        return None;
+    }
+    match tokenize(&rule.pattern, &Syntax::C).as_slice() {
+        [token] if token.is_ident() => Some(token.text.clone()),
+        _ => None,
+    }
+}
+
+fn finding(
+    path: &Path,
+    src: &str,
+    rule: &Rule,
+    span: (Pos, Pos),
+    message: Option<String>,
+) -> IllegalExpr {
+    let (start, end) = span;
+    IllegalExpr {
+        content: Some(source_line(src, start.0)),
+        rule: Some(rule.name.clone()),
+        message: rule.message.clone().or(message),
+        severity: rule.severity,
+        action: rule.action(),
+        violates: rule.category.clone(),
+        loc: (start.1, start.0),
+        end: (end.1, end.0),
+        path: path.to_path_buf(),
+    }
+}
+
+/// A `##` paste in a macro body: pieces of text and the parameters between them.
+enum Piece {
+    Text(String),
+    Param(usize),
+}
+
+/// Names pasted together with `##` in `#define` bodies. Pastes of literal pieces are resolved
+/// at the definition; pastes of parameters at every use of a function-like macro.
+fn pastes(tokens: &[Token], directives: &[&[Token]]) -> Vec<(String, Pos, Pos)> {
+    let mut ret = vec![];
+    let mut macros: HashMap<&str, (usize, Vec<Vec<Piece>>)> = HashMap::new();
+    for directive in directives {
+        let [_, define, name, rest @ ..] = directive else {
+            continue;
+        };
+        if !define.is("define") {
+            continue;
+        }
+        // `#define F(a, b)` has no space before the parameter list.
+        let mut params = vec![];
+        let mut body = rest;
+        if rest
+            .first()
+            .is_some_and(|t| t.is("(") && t.start == name.end)
+        {
+            let close = rest.iter().position(|t| t.is(")")).unwrap_or(rest.len());
+            params = rest[1..close]
+                .iter()
+                .filter(|t| t.is_ident())
+                .map(|t| t.text.as_str())
+                .collect();
+            body = rest.get(close + 1..).unwrap_or_default();
+        }
+        let mut chains = vec![];
+        let mut i = 0;
+        while i < body.len() {
+            let mut chain = vec![&body[i]];
+            let mut j = i;
+            while body.get(j + 1).is_some_and(|t| t.is("#"))
+                && body.get(j + 2).is_some_and(|t| t.is("#"))
+                && let Some(next) = body.get(j + 3)
+            {
+                chain.push(next);
+                j += 3;
+            }
+            i = j + 1;
+            if chain.len() < 2 {
+                continue;
+            }
+            let pieces: Vec<Piece> = chain
+                .iter()
+                .map(|t| match params.iter().position(|p| t.is(p)) {
+                    Some(idx) => Piece::Param(idx),
+                    None => Piece::Text(t.text.clone()),
+                })
+                .collect();
+            if pieces.iter().all(|piece| matches!(piece, Piece::Text(_))) {
+                let pasted: String = chain.iter().map(|t| t.text.as_str()).collect();
+                ret.push((pasted, chain[0].start, chain[chain.len() - 1].end));
+            } else {
+                chains.push(pieces);
+            }
+        }
+        if !chains.is_empty() {
+            macros.insert(&name.text, (params.len(), chains));
+        }
+    }
+    if macros.is_empty() {
+        return ret;
+    }
+    for i in 0..tokens.len() {
+        let Some((arity, chains)) = macros.get(tokens[i].text.as_str()) else {
+            continue;
+        };
+        if !tokens.get(i + 1).is_some_and(|t| t.is("(")) {
+            continue;
+        }
+        let mut args = vec![String::new()];
+        let mut depth = 0usize;
+        let mut end = tokens[i].end;
+        for token in &tokens[i + 1..] {
+            end = token.end;
+            if token.is("(") {
+                depth += 1;
+                if depth == 1 {
+                    continue;
+                }
+            } else if token.is(")") {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            } else if token.is(",") && depth == 1 {
+                args.push(String::new());
+                continue;
+            }
+            if let Some(arg) = args.last_mut() {
+                arg.push_str(&token.text);
+            }
+        }
+        if args.len() != *arity {
+            continue;
+        }
+        for chain in chains {
+            let pasted: String = chain
+                .iter()
+                .map(|piece| match piece {
+                    Piece::Text(text) => text.as_str(),
+                    Piece::Param(idx) => args[*idx].as_str(),
+                })
+                .collect();
+            ret.push((pasted, tokens[i].start, end));
+        }
+    }
+    ret
+}
+
+pub fn check_source(path: &Path, src: &str, lang: &Language, rules: &[&Rule]) -> Vec<IllegalExpr> {
+    let tokens = tokenize(src, &Syntax::of(lang));
+    let directives = directives(&tokens);
+    let locals = locals(&tokens, &directives);
+    // `#include <signal.h>` and `#ifdef wait` do not use anything; `#define` bodies do.
+    let mut skipped = HashSet::new();
+    for directive in &directives {
+        let skip = if directive.get(1).is_some_and(|t| t.is("define")) {
+            &directive[..directive.len().min(3)]
+        } else {
+            directive
+        };
+        skipped.extend(skip.iter().map(|t| t.start));
+    }
+    let (names, others): (Vec<_>, Vec<_>) = rules
+        .iter()
+        .copied()
+        .partition(|rule| single_name(rule).is_some());
+    let names: Vec<(&Rule, String)> = names
+        .into_iter()
+        .filter_map(|rule| Some((rule, single_name(rule)?)))
+        .collect();
+    let matching = |name: &str| {
+        names
+            .iter()
+            .find(|(_, pattern)| name == pattern || canonical(name) == canonical(pattern))
+    };
+    let mut ret = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Ident || skipped.contains(&token.start) || !is_free(&tokens, i)
+        {
+            continue;
+        }
+        let name = canonical(&token.text);
+        let call = tokens.get(i + 1).is_some_and(|t| t.is("("));
+        if locals.functions.contains(name) || (!call && locals.variables.contains(name)) {
+            continue;
+        }
+        if let Some((rule, pattern)) = matching(&token.text) {
+            let message = (token.text != *pattern).then(|| format!("`{}` is `{name}`", token.text));
+            ret.push(finding(path, src, rule, (token.start, token.end), message));
+        }
+    }
+    for (pasted, start, end) in pastes(&tokens, &directives) {
+        if let Some((rule, _)) = matching(&pasted) {
+            let message = format!("a macro pastes `{pasted}` together");
+            ret.push(finding(path, src, rule, (start, end), Some(message)));
+        }
+    }
+    ret.extend(static_check::check_source(path, src, lang, &others));
+    ret.sort_by_key(|expr| (expr.loc.1, expr.loc.0));
+    ret
+}
+
+/// Whether `allowed` permits the header `header`. `dir/*` permits the headers directly in
+/// `dir`, and `dir/**` those in its subdirectories too.
+fn permits(allowed: &[String], header: &str) -> bool {
+    allowed.iter().any(|entry| {
+        if let Some(dir) = entry.strip_suffix("/**") {
+            header.starts_with(&format!("{dir}/"))
+        } else if let Some(dir) = entry.strip_suffix("/*") {
+            header
+                .strip_prefix(&format!("{dir}/"))
+                .is_some_and(|rest| !rest.contains('/'))
+        } else {
+            header == entry
+        }
+    })
+}
+
+/// Findings for every `#include` the `imports` allowlist does not permit. Quoted includes of
+/// the submission's own files are always permitted, and an include whose header comes from a
+/// macro never is.
+pub fn check_includes(path: &Path, src: &str, allowed: &[String]) -> Vec<IllegalExpr> {
+    let tokens = tokenize(src, &Syntax::C);
+    let mut ret = vec![];
+    for directive in directives(&tokens) {
+        let [hash, include, header @ ..] = directive else {
+            continue;
+        };
+        if !include.is("include") && !include.is("include_next") {
+            continue;
+        }
+        let (Some(first), Some(last)) = (header.first(), header.last()) else {
+            continue;
+        };
+        let message = match first.kind {
+            TokenKind::Str => {
+                let name = first.text.trim_matches('"');
+                let own = path.parent().is_some_and(|dir| dir.join(name).is_file());
+                if own || permits(allowed, name) {
+                    continue;
+                }
+                format!("`{name}` is not in the include allowlist")
+            }
+            _ if first.is("<") => {
+                let close = header
+                    .iter()
+                    .position(|t| t.is(">"))
+                    .unwrap_or(header.len());
+                let name: String = header[1..close].iter().map(|t| t.text.as_str()).collect();
+                if permits(allowed, &name) {
+                    continue;
+                }
+                format!("`{name}` is not in the include allowlist")
+            }
+            _ => "the header is computed by a macro".to_owned(),
+        };
+        ret.push(IllegalExpr {
+            content: Some(source_line(src, hash.start.0)),
+            rule: Some("imports".to_owned()),
+            message: Some(message),
+            severity: Severity::Error,
+            action: rules::category_action(&Allow::Import),
+            violates: Some(Allow::Import),
+            loc: (hash.start.1, hash.start.0),
+            end: (last.end.1, last.end.0),
+            path: path.to_path_buf(),
+        });
+    }
+    ret
+}
+
+pub fn check(path: &PathBuf) -> Result<Vec<IllegalExpr>> {
+    let lang: Language = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .into();
+    let prohibited = static_check::prohibited()?;
+    let src = read_to_string(path)?;
+    Ok(check_source(
+        path,
+        &src,
+        &lang,
+        &rules::active(&lang, &prohibited),
+    ))
+}
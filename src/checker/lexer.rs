@@ -0,0 +1,386 @@
+//! Tokenizer shared by the checkers. Comments and whitespace are dropped; literals are kept as
+//! single tokens so their contents can never match a rule.
+
+use crate::executable::Language;
+
+/// Zero-based line and column, counted in characters.
+pub type Pos = (usize, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Ident,
+    Number,
+    Str,
+    Char,
+    Punct,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub start: Pos,
+    /// Position just past the last character.
+    pub end: Pos,
+    /// Whether any character of the token was written as a Unicode escape.
+    pub escaped: bool,
+}
+
+impl Token {
+    pub fn is(&self, text: &str) -> bool {
+        self.text == text
+    }
+
+    pub fn is_ident(&self) -> bool {
+        self.kind == TokenKind::Ident
+    }
+}
+
+/// Lexical rules of a language, as far as the checkers need them.
+#[derive(Debug, Clone, Copy)]
+pub struct Syntax {
+    pub line_comments: &'static [&'static str],
+    pub block_comment: Option<(&'static str, &'static str)>,
+    /// Rust block comments nest.
+    pub nested_comments: bool,
+    /// Delimiters of multi-line strings, such as Java text blocks and Python docstrings.
+    pub triple_quotes: &'static [&'static str],
+    /// Whether `'` starts a string rather than a character literal.
+    pub single_quote_strings: bool,
+    /// Letters that may prefix a string literal, such as Python's `r`, `b` and `f`.
+    pub string_prefixes: &'static str,
+    /// Rust raw strings (`r#"…"#`) and C++ raw strings (`R"(…)"`).
+    pub raw_strings: bool,
+    /// Rust lifetimes, which begin with `'` but are not character literals.
+    pub lifetimes: bool,
+    /// Java `\uXXXX` escapes, which are translated before anything else, anywhere in the file.
+    pub unicode_escapes: bool,
+}
+
+impl Syntax {
+    pub const C: Self = Self {
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        nested_comments: false,
+        triple_quotes: &[],
+        single_quote_strings: false,
+        string_prefixes: "LuU8",
+        raw_strings: true,
+        lifetimes: false,
+        unicode_escapes: false,
+    };
+    pub const JAVA: Self = Self {
+        triple_quotes: &["\"\"\""],
+        string_prefixes: "",
+        raw_strings: false,
+        unicode_escapes: true,
+        ..Self::C
+    };
+    pub const RUST: Self = Self {
+        nested_comments: true,
+        string_prefixes: "b",
+        lifetimes: true,
+        ..Self::C
+    };
+    pub const PYTHON: Self = Self {
+        line_comments: &["#"],
+        block_comment: None,
+        nested_comments: false,
+        triple_quotes: &["\"\"\"", "'''"],
+        single_quote_strings: true,
+        string_prefixes: "rRbBfFuU",
+        raw_strings: false,
+        lifetimes: false,
+        unicode_escapes: false,
+    };
+
+    pub const fn of(lang: &Language) -> Self {
+        match lang {
+            Language::Java => Self::JAVA,
+            Language::Rust => Self::RUST,
+            Language::Python => Self::PYTHON,
+            Language::C | Language::Cpp | Language::Unknown(_) | Language::Guess => Self::C,
+        }
+    }
+}
+
+struct Cursor {
+    chars: Vec<char>,
+    /// Where each character starts in the original source, followed by the end of the source.
+    origin: Vec<Pos>,
+    /// Whether each character was written as a Unicode escape.
+    escaped: Vec<bool>,
+    idx: usize,
+}
+
+impl Cursor {
+    fn new(src: &str, unicode_escapes: bool) -> Self {
+        let raw: Vec<char> = src.chars().collect();
+        let mut positions = Vec::with_capacity(raw.len() + 1);
+        let mut pos = (0, 0);
+        for &c in &raw {
+            positions.push(pos);
+            pos = if c == '\n' {
+                (pos.0 + 1, 0)
+            } else {
+                (pos.0, pos.1 + 1)
+            };
+        }
+        positions.push(pos);
+        let mut cur = Self {
+            chars: Vec::with_capacity(raw.len()),
+            origin: Vec::with_capacity(raw.len() + 1),
+            escaped: Vec::with_capacity(raw.len()),
+            idx: 0,
+        };
+        let mut i = 0;
+        // a backslash only starts an escape after an even number of backslashes.
+        let mut backslashes = 0usize;
+        while i < raw.len() {
+            if unicode_escapes
+                && backslashes.is_multiple_of(2)
+                && let Some((c, len)) = unicode_escape(&raw[i..])
+            {
+                cur.chars.push(c);
+                cur.origin.push(positions[i]);
+                cur.escaped.push(true);
+                i += len;
+                backslashes = 0;
+                continue;
+            }
+            backslashes = if raw[i] == '\\' { backslashes + 1 } else { 0 };
+            cur.chars.push(raw[i]);
+            cur.origin.push(positions[i]);
+            cur.escaped.push(false);
+            i += 1;
+        }
+        cur.origin.push(pos);
+        cur
+    }
+
+    fn pos(&self) -> Pos {
+        self.origin[self.idx]
+    }
+
+    fn peek(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.idx + ahead).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.peek(i) == Some(c))
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.idx += 1;
+        Some(c)
+    }
+
+    fn bump_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.bump();
+        }
+    }
+
+    fn text(&self, from: usize) -> String {
+        self.chars[from..self.idx].iter().collect()
+    }
+}
+
+/// The character and length of a `\\uXXXX` escape (any number of `u`s) at the start of `raw`.
+fn unicode_escape(raw: &[char]) -> Option<(char, usize)> {
+    if raw.first() != Some(&'\\') {
+        return None;
+    }
+    let us = raw[1..].iter().take_while(|&&c| c == 'u').count();
+    if us == 0 {
+        return None;
+    }
+    let digits: String = raw.get(1 + us..5 + us)?.iter().collect();
+    let code = u32::from_str_radix(&digits, 16).ok()?;
+    Some((char::from_u32(code)?, 5 + us))
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Splits Java source into tokens.
+pub fn tokenize_java(src: &str) -> Vec<Token> {
+    tokenize(src, &Syntax::JAVA)
+}
+
+/// Splits source into tokens following `syntax`.
+pub fn tokenize(src: &str, syntax: &Syntax) -> Vec<Token> {
+    let mut cur = Cursor::new(src, syntax.unicode_escapes);
+    let mut ret = vec![];
+    while let Some(c) = cur.peek(0) {
+        let start = cur.pos();
+        let from = cur.idx;
+        let kind = if c.is_whitespace() {
+            cur.bump();
+            continue;
+        } else if syntax.line_comments.iter().any(|p| cur.starts_with(p)) {
+            while cur.peek(0).is_some_and(|c| c != '\n') {
+                cur.bump();
+            }
+            continue;
+        } else if let Some((open, close)) = syntax.block_comment
+            && cur.starts_with(open)
+        {
+            block_comment(&mut cur, open, close, syntax.nested_comments);
+            continue;
+        } else if syntax.raw_strings && raw_string(&mut cur) {
+            TokenKind::Str
+        } else if let Some(len) = string_prefix(&cur, syntax) {
+            cur.bump_n(len);
+            string(&mut cur, syntax);
+            TokenKind::Str
+        } else if is_ident_start(c) {
+            while cur.peek(0).is_some_and(is_ident_continue) {
+                cur.bump();
+            }
+            TokenKind::Ident
+        } else if c.is_ascii_digit() {
+            while cur
+                .peek(0)
+                .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.')
+            {
+                cur.bump();
+            }
+            TokenKind::Number
+        } else if c == '"' || (c == '\'' && syntax.single_quote_strings) {
+            string(&mut cur, syntax);
+            TokenKind::Str
+        } else if c == '\'' && !(syntax.lifetimes && is_lifetime(&cur)) {
+            string(&mut cur, syntax);
+            TokenKind::Char
+        } else {
+            cur.bump();
+            TokenKind::Punct
+        };
+        ret.push(Token {
+            kind,
+            text: cur.text(from),
+            start,
+            end: cur.pos(),
+            escaped: cur.escaped[from..cur.idx].contains(&true),
+        });
+    }
+    ret
+}
+
+fn block_comment(cur: &mut Cursor, open: &str, close: &str, nested: bool) {
+    cur.bump_n(open.chars().count());
+    let mut depth = 1usize;
+    while cur.peek(0).is_some() {
+        if cur.starts_with(close) {
+            cur.bump_n(close.chars().count());
+            depth -= 1;
+            if depth == 0 {
+                return;
+            }
+        } else if nested && cur.starts_with(open) {
+            cur.bump_n(open.chars().count());
+            depth += 1;
+        } else {
+            cur.bump();
+        }
+    }
+}
+
+/// Length of a string prefix such as `rb` when it is directly followed by a quote.
+fn string_prefix(cur: &Cursor, syntax: &Syntax) -> Option<usize> {
+    let len = (0..3)
+        .take_while(|&i| {
+            cur.peek(i)
+                .is_some_and(|c| syntax.string_prefixes.contains(c))
+        })
+        .count();
+    let quote = cur.peek(len)?;
+    let quoted = quote == '"' || (quote == '\'' && syntax.single_quote_strings);
+    (len > 0 && quoted).then_some(len)
+}
+
+/// Reads a quoted literal at the cursor, including triple-quoted forms.
+fn string(cur: &mut Cursor, syntax: &Syntax) {
+    if let Some(delim) = syntax.triple_quotes.iter().find(|d| cur.starts_with(d)) {
+        let len = delim.chars().count();
+        cur.bump_n(len);
+        while cur.peek(0).is_some() && !cur.starts_with(delim) {
+            if cur.bump() == Some('\\') {
+                cur.bump();
+            }
+        }
+        cur.bump_n(len);
+        return;
+    }
+    let Some(quote) = cur.bump() else {
+        return;
+    };
+    while let Some(next) = cur.peek(0) {
+        if next == '\n' {
+            break;
+        }
+        cur.bump();
+        if next == '\\' {
+            cur.bump();
+        } else if next == quote {
+            break;
+        }
+    }
+}
+
+/// Reads `r#"…"#` or `R"delim(…)delim"` at the cursor, if there is one.
+fn raw_string(cur: &mut Cursor) -> bool {
+    let prefix = (0..3)
+        .take_while(|&i| cur.peek(i).is_some_and(|c| "bLuU8rR".contains(c)))
+        .count();
+    match prefix.checked_sub(1).and_then(|last| cur.peek(last)) {
+        // Rust: r"…", r#"…"#, br##"…"##.
+        Some('r') => {
+            let hashes = (prefix..).take_while(|&i| cur.peek(i) == Some('#')).count();
+            if cur.peek(prefix + hashes) != Some('"') {
+                return false;
+            }
+            let close: String = core::iter::once('"')
+                .chain(core::iter::repeat_n('#', hashes))
+                .collect();
+            cur.bump_n(prefix + hashes + 1);
+            while cur.peek(0).is_some() && !cur.starts_with(&close) {
+                cur.bump();
+            }
+            cur.bump_n(close.chars().count());
+            true
+        }
+        // C++: R"delim(…)delim".
+        Some('R') => {
+            if cur.peek(prefix) != Some('"') {
+                return false;
+            }
+            let delim: String = (prefix + 1..)
+                .map_while(|i| cur.peek(i).filter(|&c| c != '(' && c != '"' && c != '\n'))
+                .collect();
+            if cur.peek(prefix + 1 + delim.chars().count()) != Some('(') {
+                return false;
+            }
+            let close = format!("){delim}\"");
+            cur.bump_n(prefix + delim.chars().count() + 2);
+            while cur.peek(0).is_some() && !cur.starts_with(&close) {
+                cur.bump();
+            }
+            cur.bump_n(close.chars().count());
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Whether the `'` at the cursor starts a Rust lifetime or label instead of a character.
+fn is_lifetime(cur: &Cursor) -> bool {
+    cur.peek(1).is_some_and(is_ident_start) && cur.peek(2) != Some('\'')
+}
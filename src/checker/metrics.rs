@@ -0,0 +1,198 @@
+//! Size and complexity metrics of submissions, gathered during the checker pass. They do not
+//! affect the score; they point reviewers at over-engineered or suspiciously small solutions.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::lexer::{Syntax, Token, tokenize};
+use super::require::methods;
+use crate::executable::Language;
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// Source files measured.
+    pub files: usize,
+    /// Lines with anything but whitespace on them.
+    pub lines: usize,
+    /// Lines with a comment on them, including ones that also hold code.
+    pub comment_lines: usize,
+    /// `comment_lines` over `lines`.
+    pub comment_ratio: f64,
+    /// Methods and functions.
+    pub methods: usize,
+    /// Deepest block nesting inside a method.
+    pub max_nesting: usize,
+    /// Cyclomatic complexity of all methods together.
+    pub complexity: usize,
+    /// Cyclomatic complexity of the most complex method.
+    pub max_complexity: usize,
+}
+
+impl Metrics {
+    /// Adds the metrics of another file of the same submission.
+    pub fn merge(&mut self, other: &Self) {
+        self.files += other.files;
+        self.lines += other.lines;
+        self.comment_lines += other.comment_lines;
+        self.comment_ratio = ratio(self.comment_lines, self.lines);
+        self.methods += other.methods;
+        self.max_nesting = self.max_nesting.max(other.max_nesting);
+        self.complexity += other.complexity;
+        self.max_complexity = self.max_complexity.max(other.max_complexity);
+    }
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+/// Whether a file with extension `ext` is source code that can be measured.
+pub fn is_source(ext: &str) -> bool {
+    ["java", "py", "c", "cpp", "rs"].contains(&ext)
+}
+
+/// Metrics of one source file.
+pub fn measure(src: &str, lang: &Language) -> Metrics {
+    let syntax = Syntax::of(lang);
+    let tokens = tokenize(src, &syntax);
+    // which characters of each line belong to a token; anything else that is not whitespace is
+    // part of a comment.
+    let lines: Vec<Vec<char>> = src.split('\n').map(|line| line.chars().collect()).collect();
+    let mut covered: Vec<Vec<bool>> = lines.iter().map(|line| vec![false; line.len()]).collect();
+    for token in &tokens {
+        for line in token.start.0..=token.end.0.min(lines.len().saturating_sub(1)) {
+            let from = if line == token.start.0 {
+                token.start.1
+            } else {
+                0
+            };
+            let to = if line == token.end.0 {
+                token.end.1
+            } else {
+                lines[line].len()
+            };
+            for flag in covered[line].iter_mut().take(to).skip(from) {
+                *flag = true;
+            }
+        }
+    }
+    let mut metrics = Metrics {
+        files: 1,
+        ..Metrics::default()
+    };
+    for (line, covered) in lines.iter().zip(&covered) {
+        if line.iter().all(|c| c.is_whitespace()) {
+            continue;
+        }
+        metrics.lines += 1;
+        if line
+            .iter()
+            .zip(covered)
+            .any(|(c, covered)| !covered && !c.is_whitespace())
+        {
+            metrics.comment_lines += 1;
+        }
+    }
+    metrics.comment_ratio = ratio(metrics.comment_lines, metrics.lines);
+    for method in methods(&tokens, lang) {
+        let complexity = 1 + decisions(method.body, lang);
+        metrics.methods += 1;
+        metrics.complexity += complexity;
+        metrics.max_complexity = metrics.max_complexity.max(complexity);
+        metrics.max_nesting = metrics.max_nesting.max(nesting(method.body, lang));
+    }
+    metrics
+}
+
+/// Branches in a method body: every place where control flow can take another path.
+fn decisions(body: &[Token], lang: &Language) -> usize {
+    let python = *lang == Language::Python;
+    let keywords: &[&str] = if python {
+        &["if", "elif", "for", "while", "except", "case", "and", "or"]
+    } else {
+        &["if", "for", "while", "case", "catch"]
+    };
+    (0..body.len())
+        .filter(|&i| {
+            let token = &body[i];
+            let next = body.get(i + 1);
+            if token.is_ident() {
+                return keywords.contains(&token.text.as_str());
+            }
+            if python {
+                return false;
+            }
+            // `&&` and `||` are two tokens each; count the first.
+            let doubled = |op: &str| {
+                token.is(op) && next.is_some_and(|next| next.is(op) && next.start == token.end)
+            };
+            let after_doubled =
+                i > 0 && body[i - 1].is(&token.text) && body[i - 1].end == token.start;
+            // the ternary operator, not a Java wildcard or a Rust `?`.
+            let ternary = token.is("?")
+                && *lang != Language::Rust
+                && !(i > 0 && body[i - 1].is("<"))
+                && !next
+                    .is_some_and(|next| [">", ",", "extends", "super"].iter().any(|t| next.is(t)));
+            ((doubled("&") || doubled("|")) && !after_doubled) || ternary
+        })
+        .count()
+}
+
+/// Deepest nesting of blocks in a method body: braces, or indentation for Python.
+fn nesting(body: &[Token], lang: &Language) -> usize {
+    let mut max = 0;
+    if *lang == Language::Python {
+        // indentation of each open block, starting with the body itself.
+        let mut levels: Vec<usize> = vec![];
+        let mut line = None;
+        // lines continued inside brackets do not open blocks.
+        let mut brackets = 0usize;
+        for token in body {
+            if line != Some(token.start.0) && brackets == 0 {
+                line = Some(token.start.0);
+                while levels.last().is_some_and(|&level| level > token.start.1) {
+                    levels.pop();
+                }
+                if levels.last().is_none_or(|&level| level < token.start.1) {
+                    levels.push(token.start.1);
+                }
+                max = max.max(levels.len().saturating_sub(1));
+            }
+            if ["(", "[", "{"].iter().any(|open| token.is(open)) {
+                brackets += 1;
+            } else if [")", "]", "}"].iter().any(|close| token.is(close)) {
+                brackets = brackets.saturating_sub(1);
+            }
+        }
+        return max;
+    }
+    let mut depth = 0usize;
+    for token in body {
+        if token.is("{") {
+            depth += 1;
+            max = max.max(depth);
+        } else if token.is("}") {
+            depth = depth.saturating_sub(1);
+        }
+    }
+    max
+}
+
+/// Adds up the metrics of every file by the submission it belongs to.
+pub fn by_submission(
+    files: &HashMap<PathBuf, Metrics>,
+    submission_of: impl Fn(&Path) -> PathBuf,
+) -> HashMap<PathBuf, Metrics> {
+    let mut ret: HashMap<PathBuf, Metrics> = HashMap::new();
+    for (path, metrics) in files {
+        ret.entry(submission_of(path)).or_default().merge(metrics);
+    }
+    ret
+}
@@ -0,0 +1,631 @@
+//! Python front end for `Type::AST`.
+//!
+//! Like the Java front end, this works on tokens rather than a full parse. It follows `import`
+//! and `from … import` statements with their aliases, `from m import *`, builtins, and
+//! `__import__`, `importlib.import_module` and `getattr` with constant names, so `o.system`
+//! after `import os as o` and `getattr(__import__("os"), "sys" + "tem")` both resolve to
+//! `os.system`. Function and class bodies get their own scope by indentation; other blocks do
+//! not, and a name keeps its last binding in its scope.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use super::lexer::{Pos, Syntax, Token, TokenKind, tokenize};
+use super::require::skip_balanced;
+use super::rules::{self, Rule, Severity};
+use super::static_check::{self, Allow};
+use super::{IllegalExpr, source_line};
+use crate::executable::Language;
+
+/// Active Python rules with a `resolved` name. A name covers everything reached through it,
+/// so `subprocess` also covers `subprocess.run`.
+fn resolved_rules(prohibited: &[Allow]) -> Vec<(&'static Rule, &'static str)> {
+    rules::resolved(&Language::Python, prohibited)
+        .into_iter()
+        .filter_map(|rule| Some((rule, rule.resolved.as_deref()?)))
+        .collect()
+}
+
+/// Builtins that can take part in a match. Other builtins resolve to nothing, like locals.
+const BUILTINS: [&str; 10] = [
+    "open",
+    "eval",
+    "exec",
+    "compile",
+    "__import__",
+    "getattr",
+    "globals",
+    "vars",
+    "exit",
+    "quit",
+];
+
+/// Whether the resolved name `name` is `pattern` or something reached through it.
+fn covers(pattern: &str, name: &str) -> bool {
+    name.strip_prefix(pattern)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// An expression such as `o.system` in `o.system(cmd)`, resolved one step at a time.
+#[derive(Debug, Clone)]
+struct Reference {
+    start: Pos,
+    /// The names each step can resolve to, and where the step ends. A name from a
+    /// `from m import *` can resolve to more than one.
+    steps: Vec<(Vec<String>, Pos)>,
+    /// Whether the first name was only found through a `from m import *`.
+    star: bool,
+}
+
+/// What a Python source refers to.
+#[derive(Debug, Default)]
+pub struct Module {
+    references: Vec<Reference>,
+    /// Modules imported with `from m import *`.
+    star: Vec<String>,
+    /// Modules and names imported by statements, `__import__` or `import_module`.
+    pub imports: Vec<(String, Pos, Pos)>,
+    /// Imports and attribute lookups whose names are only known at run time.
+    pub computed: Vec<(String, Pos, Pos)>,
+}
+
+struct Scope {
+    /// Indentation of the `def` or `class` line; the scope ends at the next line indented no
+    /// deeper.
+    indent: usize,
+    /// Bound names, with the module or object they refer to, or `None` for anything else.
+    names: HashMap<String, Option<String>>,
+}
+
+struct Resolver<'a> {
+    tokens: &'a [Token],
+    scopes: Vec<Scope>,
+    module: Module,
+}
+
+fn is(tokens: &[Token], i: usize, text: &str) -> bool {
+    tokens.get(i).is_some_and(|token| token.is(text))
+}
+
+/// Value of a string literal without prefixes or quotes. f-strings and bytes are not constants.
+fn unquote(token: &Token) -> Option<&str> {
+    if token.kind != TokenKind::Str {
+        return None;
+    }
+    let body = token.text.trim_start_matches(['r', 'R', 'u', 'U']);
+    ["\"\"\"", "'''", "\"", "'"]
+        .iter()
+        .find_map(|quote| body.strip_prefix(quote)?.strip_suffix(quote))
+}
+
+/// The string literals at `i` joined the way Python joins adjacent literals and literals added
+/// with `+`, and the index after them.
+fn literal(tokens: &[Token], mut i: usize) -> Option<(String, usize)> {
+    let mut value = None::<String>;
+    while let Some(part) = tokens.get(i).and_then(unquote) {
+        value.get_or_insert_default().push_str(part);
+        i += 1;
+        if is(tokens, i, "+") && tokens.get(i + 1).and_then(unquote).is_some() {
+            i += 1;
+        }
+    }
+    value.map(|value| (value, i))
+}
+
+/// A dotted name such as `os.path` at `i`, where it ends, and the index after it.
+fn dotted(tokens: &[Token], mut i: usize) -> Option<(String, Pos, usize)> {
+    let first = tokens.get(i).filter(|token| token.is_ident())?;
+    let mut name = first.text.clone();
+    let mut end = first.end;
+    i += 1;
+    while is(tokens, i, ".")
+        && let Some(part) = tokens.get(i + 1).filter(|token| token.is_ident())
+    {
+        name.push('.');
+        name.push_str(&part.text);
+        end = part.end;
+        i += 2;
+    }
+    Some((name, end, i))
+}
+
+impl Resolver<'_> {
+    fn bind(&mut self, name: &str, value: Option<String>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.insert(name.to_owned(), value);
+        }
+    }
+
+    /// Whether `name` resolves without `from m import *`.
+    fn is_bound(&self, name: &str) -> bool {
+        self.scopes
+            .iter()
+            .any(|scope| scope.names.contains_key(name))
+            || name == "__builtins__"
+            || BUILTINS.contains(&name)
+    }
+
+    fn lookup(&self, name: &str) -> Vec<String> {
+        if let Some(bound) = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.get(name))
+        {
+            return bound.iter().cloned().collect();
+        }
+        if name == "__builtins__" {
+            return vec!["builtins".to_owned()];
+        }
+        if BUILTINS.contains(&name) {
+            return vec![format!("builtins.{name}")];
+        }
+        self.module
+            .star
+            .iter()
+            .map(|module| format!("{module}.{name}"))
+            .collect()
+    }
+
+    fn run(&mut self) {
+        let tokens = self.tokens;
+        let mut depth = 0usize;
+        let mut indent = 0;
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if depth == 0 && (i == 0 || tokens[i - 1].end.0 < token.start.0) {
+                indent = token.start.1;
+                while self.scopes.len() > 1
+                    && self
+                        .scopes
+                        .last()
+                        .is_some_and(|scope| indent <= scope.indent)
+                {
+                    self.scopes.pop();
+                }
+            }
+            if !token.is_ident() {
+                if ["(", "[", "{"].contains(&token.text.as_str()) {
+                    depth += 1;
+                } else if [")", "]", "}"].contains(&token.text.as_str()) {
+                    depth = depth.saturating_sub(1);
+                }
+                i += 1;
+                continue;
+            }
+            if i > 0 && tokens[i - 1].is(".") {
+                i += 1;
+                continue;
+            }
+            let assigns = is(tokens, i + 1, "=") && !is(tokens, i + 2, "=");
+            i = match token.text.as_str() {
+                "import" => self.import(i),
+                "from" => self.import_from(i).unwrap_or(i + 1),
+                "def" | "class" => self.declaration(i, indent),
+                "for" => {
+                    let mut j = i + 1;
+                    while tokens.get(j).is_some_and(|t| !t.is("in") && !t.is(":")) {
+                        if tokens[j].is_ident() {
+                            self.bind(&tokens[j].text, None);
+                        }
+                        j += 1;
+                    }
+                    j
+                }
+                // `with … as name` and `except … as name`.
+                "as" => {
+                    if let Some(name) = tokens.get(i + 1).filter(|t| t.is_ident()) {
+                        self.bind(&name.text, None);
+                    }
+                    i + 2
+                }
+                // keyword arguments name parameters, not variables.
+                _ if assigns && depth > 0 => i + 1,
+                _ if assigns || (is(tokens, i + 1, ":") && is(tokens, i + 2, "=")) => {
+                    self.bind(&token.text, None);
+                    i + 1
+                }
+                _ => {
+                    let (reference, end) = self.chain(i);
+                    if !reference.steps.is_empty() {
+                        self.module.references.push(reference);
+                    }
+                    end
+                }
+            };
+        }
+    }
+
+    /// `import a.b as c, d`. Returns the index after the statement.
+    fn import(&mut self, i: usize) -> usize {
+        let tokens = self.tokens;
+        let mut j = i + 1;
+        while let Some((name, end, next)) = dotted(tokens, j) {
+            let start = tokens[j].start;
+            self.module.imports.push((name.clone(), start, end));
+            self.module.references.push(Reference {
+                start,
+                steps: vec![(vec![name.clone()], end)],
+                star: false,
+            });
+            j = next;
+            if is(tokens, j, "as")
+                && let Some(alias) = tokens.get(j + 1).filter(|t| t.is_ident())
+            {
+                self.bind(&alias.text, Some(name));
+                j += 2;
+            } else {
+                // `import a.b` binds `a`.
+                let root = name.split('.').next().unwrap_or_default().to_owned();
+                self.bind(&root, Some(root.clone()));
+            }
+            if !is(tokens, j, ",") {
+                break;
+            }
+            j += 1;
+        }
+        j
+    }
+
+    /// `from a.b import c as d, e` and `from a import *`, or `None` if the `from` at `i` is
+    /// not an import, as in `yield from`.
+    fn import_from(&mut self, i: usize) -> Option<usize> {
+        let tokens = self.tokens;
+        let mut j = i + 1;
+        let mut relative = false;
+        while is(tokens, j, ".") {
+            relative = true;
+            j += 1;
+        }
+        let module = dotted(tokens, j).map(|(name, _, next)| {
+            j = next;
+            name
+        });
+        if !is(tokens, j, "import") || (module.is_none() && !relative) {
+            return None;
+        }
+        // names from the submission's own modules refer to nothing that can match.
+        let module = module.filter(|_| !relative);
+        j += 1;
+        let parenthesized = is(tokens, j, "(");
+        if parenthesized {
+            j += 1;
+        }
+        while let Some(token) = tokens.get(j) {
+            if token.is("*") {
+                if let Some(module) = &module {
+                    self.module
+                        .imports
+                        .push((module.clone(), token.start, token.end));
+                    self.module.references.push(Reference {
+                        start: token.start,
+                        steps: vec![(vec![module.clone()], token.end)],
+                        star: false,
+                    });
+                    self.module.star.push(module.clone());
+                }
+                j += 1;
+            } else if token.is_ident() {
+                let fq = module.as_ref().map(|m| format!("{m}.{}", token.text));
+                if let Some(fq) = &fq {
+                    self.module
+                        .imports
+                        .push((fq.clone(), token.start, token.end));
+                    self.module.references.push(Reference {
+                        start: token.start,
+                        steps: vec![(vec![fq.clone()], token.end)],
+                        star: false,
+                    });
+                }
+                j += 1;
+                let mut name = &token.text;
+                if is(tokens, j, "as")
+                    && let Some(alias) = tokens.get(j + 1).filter(|t| t.is_ident())
+                {
+                    name = &alias.text;
+                    j += 2;
+                }
+                self.bind(name, fq);
+            } else {
+                break;
+            }
+            if !is(tokens, j, ",") {
+                break;
+            }
+            j += 1;
+        }
+        if parenthesized && is(tokens, j, ")") {
+            j += 1;
+        }
+        Some(j)
+    }
+
+    /// `def` and `class`: binds the name and opens a scope for the body, with the parameters.
+    fn declaration(&mut self, i: usize, indent: usize) -> usize {
+        let tokens = self.tokens;
+        let Some(name) = tokens.get(i + 1).filter(|t| t.is_ident()) else {
+            return i + 1;
+        };
+        self.bind(&name.text, None);
+        self.scopes.push(Scope {
+            indent,
+            names: HashMap::new(),
+        });
+        if tokens[i].is("def") && is(tokens, i + 2, "(") {
+            let end = skip_balanced(tokens, i + 2, "(", ")");
+            let mut depth = 0usize;
+            for j in i + 2..end {
+                let token = &tokens[j];
+                if ["(", "[", "{"].contains(&token.text.as_str()) {
+                    depth += 1;
+                } else if [")", "]", "}"].contains(&token.text.as_str()) {
+                    depth = depth.saturating_sub(1);
+                } else if depth == 1
+                    && token.is_ident()
+                    && ["(", ",", "*"].iter().any(|p| tokens[j - 1].is(p))
+                {
+                    self.bind(&token.text, None);
+                }
+            }
+        }
+        i + 2
+    }
+
+    /// Resolves the expression starting with the name at `i`, following attributes and the
+    /// calls that look names up. Returns the reference and the index after it.
+    fn chain(&mut self, i: usize) -> (Reference, usize) {
+        let tokens = self.tokens;
+        let root = &tokens[i];
+        let mut reference = Reference {
+            start: root.start,
+            steps: vec![],
+            star: !self.is_bound(&root.text),
+        };
+        let mut names = self.lookup(&root.text);
+        if names.is_empty() {
+            return (reference, i + 1);
+        }
+        reference.steps.push((names.clone(), root.end));
+        let mut j = i + 1;
+        loop {
+            if is(tokens, j, ".")
+                && let Some(attr) = tokens.get(j + 1).filter(|t| t.is_ident())
+            {
+                // `m.__dict__["name"]` is `m.name`.
+                let key = (attr.is("__dict__") && is(tokens, j + 2, "["))
+                    .then(|| literal(tokens, j + 3))
+                    .flatten()
+                    .filter(|(_, k)| is(tokens, *k, "]"));
+                let (attr, next) = match key {
+                    Some((key, k)) => (key, k + 1),
+                    None => (attr.text.clone(), j + 2),
+                };
+                names = names.iter().map(|name| format!("{name}.{attr}")).collect();
+                j = next;
+                reference.steps.push((names.clone(), tokens[j - 1].end));
+                continue;
+            }
+            if !is(tokens, j, "(") {
+                break;
+            }
+            let close = skip_balanced(tokens, j, "(", ")");
+            let end = tokens[close - 1].end;
+            let constant_arg = |k: usize| is(tokens, k, ")") || is(tokens, k, ",");
+            if names
+                .iter()
+                .any(|name| name == "builtins.__import__" || name == "importlib.import_module")
+            {
+                match literal(tokens, j + 1) {
+                    Some((module, k)) if constant_arg(k) => {
+                        self.module.imports.push((module.clone(), root.start, end));
+                        names = vec![module];
+                    }
+                    _ => {
+                        self.module.computed.push((
+                            format!("`{}` imports a module by a computed name", root.text),
+                            root.start,
+                            end,
+                        ));
+                        j = close;
+                        break;
+                    }
+                }
+            } else if names.iter().any(|name| name == "builtins.getattr")
+                && tokens.get(j + 1).is_some_and(Token::is_ident)
+            {
+                let (inner, k) = self.chain(j + 1);
+                let Some((owners, _)) = inner.steps.last().cloned().filter(|_| !inner.star) else {
+                    break;
+                };
+                self.module.references.push(inner);
+                match literal(tokens, k + 1) {
+                    Some((attr, m)) if is(tokens, k, ",") && constant_arg(m) => {
+                        names = owners
+                            .iter()
+                            .map(|owner| format!("{owner}.{attr}"))
+                            .collect();
+                    }
+                    _ => {
+                        self.module.computed.push((
+                            format!("`getattr` looks up a computed attribute of `{}`", owners[0]),
+                            root.start,
+                            end,
+                        ));
+                        j = close;
+                        break;
+                    }
+                }
+            } else {
+                break;
+            }
+            j = close;
+            reference.steps.push((names.clone(), end));
+        }
+        (reference, j)
+    }
+}
+
+/// Resolves everything a Python source refers to.
+pub fn resolve(tokens: &[Token]) -> Module {
+    let mut resolver = Resolver {
+        tokens,
+        scopes: vec![Scope {
+            indent: 0,
+            names: HashMap::new(),
+        }],
+        module: Module::default(),
+    };
+    resolver.run();
+    resolver.module
+}
+
+/// A reference to a name a resolved rule covers.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub rule: &'static Rule,
+    pub pattern: &'static str,
+    pub start: Pos,
+    pub end: Pos,
+}
+
+pub fn find(module: &Module, prohibited: &[Allow]) -> Vec<Hit> {
+    let rules = resolved_rules(prohibited);
+    let matching = |names: &[String], star: bool| {
+        rules
+            .iter()
+            // a pattern covering a whole `*` module already matched the import.
+            .filter(|(_, pattern)| !star || !module.star.iter().any(|m| covers(pattern, m)))
+            .find(|(_, pattern)| names.iter().any(|name| covers(pattern, name)))
+            .copied()
+    };
+    let mut hits = vec![];
+    for reference in &module.references {
+        // `subprocess.run` is reported once, for `subprocess`.
+        let mut previous = None;
+        for (names, end) in &reference.steps {
+            let found = matching(names, reference.star);
+            if let Some((rule, pattern)) = found
+                && previous != Some(pattern)
+            {
+                hits.push(Hit {
+                    rule,
+                    pattern,
+                    start: reference.start,
+                    end: *end,
+                });
+            }
+            previous = found.map(|(_, pattern)| pattern);
+        }
+    }
+    hits.sort_by(|a, b| (a.start, a.end, &a.rule.name).cmp(&(b.start, b.end, &b.rule.name)));
+    hits.dedup_by(|a, b| (a.start, a.end, &a.rule.name) == (b.start, b.end, &b.rule.name));
+    hits
+}
+
+pub fn check_source(path: &Path, src: &str, prohibited: &[Allow]) -> Vec<IllegalExpr> {
+    let module = resolve(&tokenize(src, &Syntax::PYTHON));
+    find(&module, prohibited)
+        .into_iter()
+        .map(|hit| IllegalExpr {
+            content: Some(source_line(src, hit.start.0)),
+            rule: Some(hit.rule.name.clone()),
+            message: hit
+                .rule
+                .message
+                .clone()
+                .or_else(|| Some(format!("resolves to `{}`", hit.pattern))),
+            severity: hit.rule.severity,
+            action: hit.rule.action(),
+            violates: hit.rule.category.clone(),
+            loc: (hit.start.1, hit.start.0),
+            end: (hit.end.1, hit.end.0),
+            path: path.to_path_buf(),
+        })
+        .collect()
+}
+
+/// Modules the submission provides itself: the other sources and packages next to `path`.
+fn local_modules(path: &Path) -> HashSet<String> {
+    let Some(Ok(entries)) = path.parent().map(std::fs::read_dir) else {
+        return HashSet::new();
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() || path.extension().is_some_and(|ext| ext == "py"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+        .collect()
+}
+
+/// Whether `allowed` permits importing `name`. An entry permits the module and everything in
+/// it; `pkg.*` and `pkg.**` mean the same as `pkg`.
+fn permits(allowed: &[String], name: &str) -> bool {
+    allowed.iter().any(|entry| {
+        let entry = entry
+            .strip_suffix(".**")
+            .or_else(|| entry.strip_suffix(".*"))
+            .unwrap_or(entry);
+        covers(entry, name)
+    })
+}
+
+/// Findings for every import in a Python source that the `imports` allowlist does not permit.
+/// The submission's own modules are always permitted.
+pub fn check_imports(path: &Path, src: &str, allowed: &[String]) -> Vec<IllegalExpr> {
+    let local = local_modules(path);
+    resolve(&tokenize(src, &Syntax::PYTHON))
+        .imports
+        .into_iter()
+        .filter(|(name, _, _)| {
+            !permits(allowed, name) && !local.contains(name.split('.').next().unwrap_or_default())
+        })
+        .map(|(name, start, end)| IllegalExpr {
+            content: Some(source_line(src, start.0)),
+            rule: Some("imports".to_owned()),
+            message: Some(format!("`{name}` is not in the import allowlist")),
+            severity: Severity::Error,
+            action: rules::category_action(&Allow::Import),
+            violates: Some(Allow::Import),
+            loc: (start.1, start.0),
+            end: (end.1, end.0),
+            path: path.to_path_buf(),
+        })
+        .collect()
+}
+
+/// Findings for the imports and lookups in a Python source whose names are computed.
+pub fn check_obfuscation(path: &Path, src: &str) -> Vec<IllegalExpr> {
+    resolve(&tokenize(src, &Syntax::PYTHON))
+        .computed
+        .into_iter()
+        .map(|(message, start, end)| IllegalExpr {
+            content: Some(source_line(src, start.0)),
+            rule: Some("obfuscation".to_owned()),
+            message: Some(message),
+            severity: Severity::Error,
+            action: rules::category_action(&Allow::Obfuscation),
+            violates: Some(Allow::Obfuscation),
+            loc: (start.1, start.0),
+            end: (end.1, end.0),
+            path: path.to_path_buf(),
+        })
+        .collect()
+}
+
+pub fn check(path: &PathBuf) -> Result<Vec<IllegalExpr>> {
+    let prohibited = static_check::prohibited()?;
+    let src = read_to_string(path)?;
+    let mut ret = check_source(path, &src, &prohibited);
+    ret.extend(static_check::check_source(
+        path,
+        &src,
+        &Language::Python,
+        &rules::unresolved(&Language::Python, &prohibited),
+    ));
+    ret.sort_by_key(|expr| (expr.loc.1, expr.loc.0));
+    Ok(ret)
+}
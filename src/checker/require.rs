@@ -0,0 +1,326 @@
+//! Required constructs: things a submission has to contain, such as a recursive method or a
+//! `while` loop. Unlike rules, a missing requirement does not stop the submission from running;
+//! it deducts points instead.
+
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use log::{debug, error, info};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use super::lexer::{Pos, Syntax, Token, tokenize};
+use super::static_check::occurrences;
+use crate::config::{self, CONFIG};
+use crate::executable::Language;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct Requirement {
+    pub name: String,
+    /// Languages whose files are searched; all of them when empty.
+    #[serde(default)]
+    pub lang: Vec<Language>,
+    #[serde(flatten)]
+    pub construct: Construct,
+    /// How many times the construct has to appear across the submission.
+    #[serde(default = "one")]
+    pub min: usize,
+    /// Points deducted when the requirement is not met.
+    #[serde(default)]
+    pub points: u64,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+const fn one() -> usize {
+    1
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Construct {
+    /// Methods that call themselves, either unqualified or through `this`/`self`. Mutual
+    /// recursion is not detected.
+    Recursion {
+        /// Only count this method.
+        #[serde(default)]
+        method: Option<String>,
+    },
+    /// Occurrences of tokens or a regular expression, matched like rules.
+    Pattern {
+        pattern: String,
+        #[serde(default)]
+        regex: bool,
+    },
+}
+
+/// A requirement a submission does not meet.
+#[derive(Serialize, Clone, Debug)]
+pub struct RequirementFinding {
+    pub name: String,
+    pub message: Option<String>,
+    pub min: usize,
+    pub found: usize,
+    pub points: u64,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct RequirementReport {
+    pub points_deducted: u64,
+    pub findings: Vec<RequirementFinding>,
+}
+
+/// Checks every submission in `dirs` against `CONFIG.require`.
+pub fn check_dirs(dirs: Vec<PathBuf>) -> HashMap<PathBuf, RequirementReport> {
+    let ret: HashMap<PathBuf, RequirementReport> = dirs
+        .into_iter()
+        .map(|dir| {
+            let report = check_dir(&dir, &CONFIG.require);
+            (dir, report)
+        })
+        .collect();
+    info!(
+        "Requirement check complete: {} submission(s) missing required constructs.",
+        ret.values()
+            .filter(|report| !report.findings.is_empty())
+            .count()
+    );
+    ret
+}
+
+pub fn check_dir(dir: &Path, requirements: &[Requirement]) -> RequirementReport {
+    let mut sources = vec![];
+    for entry in WalkDir::new(dir).into_iter().filter_map(Result::ok) {
+        let path = entry.path();
+        let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+            continue;
+        };
+        // a requirement met by a dependency the grader copied in is not met by the student.
+        if !path.is_file()
+            || !config::KNOWN_EXTENSIONS.contains(ext)
+            || CONFIG.is_dependency(dir, path)
+        {
+            continue;
+        }
+        match read_to_string(path) {
+            Ok(src) => sources.push((Language::from(ext), src)),
+            Err(e) => error!("Failed to read {}: {e}", path.display()),
+        }
+    }
+    let mut report = RequirementReport::default();
+    for requirement in requirements {
+        let found = count(requirement, &sources);
+        if found < requirement.min {
+            report.points_deducted += requirement.points;
+            report.findings.push(RequirementFinding {
+                name: requirement.name.clone(),
+                message: requirement.message.clone(),
+                min: requirement.min,
+                found,
+                points: requirement.points,
+            });
+        }
+    }
+    debug!(
+        "{}: {} missing requirement(s).",
+        dir.display(),
+        report.findings.len()
+    );
+    report
+}
+
+fn count(requirement: &Requirement, sources: &[(Language, String)]) -> usize {
+    let regex = match &requirement.construct {
+        Construct::Pattern {
+            pattern,
+            regex: true,
+        } => match Regex::new(pattern) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                error!(
+                    "Requirement `{}` has an invalid regex: {e}",
+                    requirement.name
+                );
+                return 0;
+            }
+        },
+        Construct::Pattern { .. } | Construct::Recursion { .. } => None,
+    };
+    sources
+        .iter()
+        .filter(|(lang, _)| requirement.lang.is_empty() || requirement.lang.contains(lang))
+        .map(|(lang, src)| {
+            let syntax = Syntax::of(lang);
+            let tokens = tokenize(src, &syntax);
+            match &requirement.construct {
+                Construct::Recursion { method } => methods(&tokens, lang)
+                    .iter()
+                    .filter(|found| method.as_deref().is_none_or(|name| name == found.name))
+                    .filter(|found| found.is_recursive())
+                    .count(),
+                Construct::Pattern { pattern, .. } => {
+                    occurrences(src, &tokens, &syntax, pattern, regex.as_ref()).len()
+                }
+            }
+        })
+        .sum()
+}
+
+/// A method or function declaration.
+pub(super) struct Method<'a> {
+    pub(super) name: &'a str,
+    /// Position of the name.
+    pub(super) start: Pos,
+    pub(super) body: &'a [Token],
+}
+
+/// Words that can directly precede a parenthesized expression followed by a block, so the
+/// identifier after them is never the name of a declaration.
+const NOT_RETURN_TYPES: [&str; 9] = [
+    "new", "return", "else", "throw", "case", "match", "in", "await", "yield",
+];
+
+/// Words that look like a call when followed by parentheses.
+const CONTROL: [&str; 8] = [
+    "if",
+    "while",
+    "for",
+    "switch",
+    "catch",
+    "synchronized",
+    "try",
+    "sizeof",
+];
+
+impl Method<'_> {
+    fn is_recursive(&self) -> bool {
+        (0..self.body.len()).any(|i| {
+            let token = &self.body[i];
+            if !token.is(self.name) || !self.body.get(i + 1).is_some_and(|next| next.is("(")) {
+                return false;
+            }
+            let back = |n: usize| i.checked_sub(n).map(|j| &self.body[j]);
+            match back(1) {
+                Some(prev) if prev.is(".") => {
+                    back(2).is_some_and(|owner| ["this", "self", "cls"].contains(&&*owner.text))
+                }
+                // Rust `Self::name(`.
+                Some(prev) if prev.is(":") => {
+                    back(2).is_some_and(|colon| colon.is(":"))
+                        && back(3).is_some_and(|owner| owner.is("Self"))
+                }
+                // a nested declaration with the same name.
+                Some(prev) if prev.is("def") || prev.is("fn") => false,
+                _ => true,
+            }
+        })
+    }
+}
+
+/// Methods and functions declared in `tokens`.
+pub(super) fn methods<'a>(tokens: &'a [Token], lang: &Language) -> Vec<Method<'a>> {
+    if *lang == Language::Python {
+        python_methods(tokens)
+    } else {
+        brace_methods(tokens)
+    }
+}
+
+/// Declarations in languages with C-like syntax: a name after a type or `fn`, a parameter list
+/// and a block, with anything but `;` in between (`throws`, `-> T`, `const`, ...).
+pub(super) fn brace_methods(tokens: &[Token]) -> Vec<Method<'_>> {
+    let mut ret = vec![];
+    for i in 1..tokens.len() {
+        let (prev, name) = (&tokens[i - 1], &tokens[i]);
+        if !name.is_ident() || CONTROL.contains(&name.text.as_str()) {
+            continue;
+        }
+        let declares = prev.is("fn")
+            || (prev.is_ident() && !NOT_RETURN_TYPES.contains(&prev.text.as_str()))
+            || [">", "]", "*", "&"].iter().any(|p| prev.is(p));
+        if !declares {
+            continue;
+        }
+        let mut j = i + 1;
+        if prev.is("fn") && tokens.get(j).is_some_and(|t| t.is("<")) {
+            j = skip_balanced(tokens, j, "<", ">");
+        }
+        if !tokens.get(j).is_some_and(|t| t.is("(")) {
+            continue;
+        }
+        j = skip_balanced(tokens, j, "(", ")");
+        while tokens
+            .get(j)
+            .is_some_and(|t| !t.is("{") && !t.is(";") && !t.is("}"))
+        {
+            j += 1;
+        }
+        if tokens.get(j).is_some_and(|t| t.is("{")) {
+            let end = skip_balanced(tokens, j, "{", "}");
+            ret.push(Method {
+                name: &name.text,
+                start: name.start,
+                body: &tokens[j + 1..end.saturating_sub(1).max(j + 1)],
+            });
+        }
+    }
+    ret
+}
+
+/// Index just past the bracket closing the one at `i`.
+pub(super) fn skip_balanced(tokens: &[Token], i: usize, open: &str, close: &str) -> usize {
+    let mut depth = 0usize;
+    for (j, token) in tokens.iter().enumerate().skip(i) {
+        if token.is(open) {
+            depth += 1;
+        } else if token.is(close) {
+            depth = depth.saturating_sub(1);
+            if depth == 0 {
+                return j + 1;
+            }
+        }
+    }
+    tokens.len()
+}
+
+/// `def` statements; the body is everything indented deeper than the `def`.
+fn python_methods(tokens: &[Token]) -> Vec<Method<'_>> {
+    let mut ret = vec![];
+    for i in 0..tokens.len() {
+        let def = &tokens[i];
+        let Some(name) = tokens.get(i + 1) else {
+            break;
+        };
+        if !def.is("def") || !name.is_ident() {
+            continue;
+        }
+        let mut j = skip_balanced(tokens, i + 2, "(", ")");
+        while tokens.get(j).is_some_and(|t| !t.is(":")) {
+            j += 1;
+        }
+        let colon_line = tokens.get(j).map_or(def.start.0, |t| t.start.0);
+        let start = (j + 1).min(tokens.len());
+        let mut end = start;
+        let mut line = colon_line;
+        while let Some(token) = tokens.get(end) {
+            if token.start.0 != line {
+                // the first token on a line gives its indentation.
+                if token.start.1 <= def.start.1 {
+                    break;
+                }
+                line = token.start.0;
+            }
+            end += 1;
+        }
+        ret.push(Method {
+            name: &name.text,
+            start: name.start,
+            body: &tokens[start..end],
+        });
+    }
+    ret
+}
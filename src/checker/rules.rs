@@ -0,0 +1,234 @@
+//! Static-analysis rules. The built-in rules ship as data in `rules.toml`; `[[rules]]` in the
+//! config adds rules of its own or replaces built-in ones with the same name.
+
+use std::sync::LazyLock;
+
+use log::{error, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::static_check::Allow;
+use crate::config::CONFIG;
+use crate::executable::Language;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Severity {
+    /// Only listed in the report unless an action says otherwise.
+    Info,
+    /// Flags the submission for review unless an action says otherwise.
+    Warning,
+    /// Blocks the submission unless an action says otherwise.
+    #[default]
+    Error,
+}
+
+/// What happens to a submission a rule matches.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Action {
+    /// The submission is not run.
+    Block,
+    /// The submission runs and is marked for review.
+    Flag,
+    /// The submission runs and loses this many points, once per rule that matches.
+    Deduct(u64),
+    /// The submission runs as if nothing matched; the finding is only listed.
+    List,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct Rule {
+    pub name: String,
+    /// Languages the rule applies to; all of them when empty.
+    #[serde(default)]
+    pub lang: Vec<Language>,
+    /// Tokens to look for, such as `Math.pow`, or a regular expression if `regex` is set.
+    #[serde(default)]
+    pub pattern: String,
+    #[serde(default)]
+    pub regex: bool,
+    /// Name the `AST` checker matches in Java and Python sources instead of `pattern`: a type
+    /// such as `java.lang.Runtime`, a package as `java.lang.reflect.*`, a method as
+    /// `java.lang.System.exit()`, or a Python name such as `os.system`, which covers everything
+    /// reached through it.
+    #[serde(default)]
+    pub resolved: Option<String>,
+    /// Shown next to every occurrence.
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Overrides the `policy` of the category and the action implied by `severity`.
+    #[serde(default)]
+    pub action: Option<Action>,
+    /// Allowing this category in `allow` turns the rule off. Rules without one always apply.
+    #[serde(default)]
+    pub category: Option<Allow>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+    #[serde(skip)]
+    compiled: Option<Regex>,
+}
+
+const fn enabled() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct Ruleset {
+    rules: Vec<Rule>,
+}
+
+static BUILTIN: LazyLock<Vec<Rule>> = LazyLock::new(|| {
+    toml::from_str::<Ruleset>(include_str!("rules.toml"))
+        .expect("built-in ruleset is valid")
+        .rules
+});
+
+/// Built-in rules merged with the configured ones, with regular expressions compiled.
+static RULESET: LazyLock<Vec<Rule>> = LazyLock::new(|| merge(&BUILTIN, &CONFIG.rules));
+
+/// Replaces rules in `base` by name and appends the rest of `overrides`. A replacement without a
+/// `pattern` or `resolved` name keeps the one it replaces. Rules with neither, or whose regular
+/// expression does not compile, are dropped.
+pub fn merge(base: &[Rule], overrides: &[Rule]) -> Vec<Rule> {
+    let mut ret = base.to_vec();
+    for rule in overrides {
+        match ret.iter_mut().find(|el| el.name == rule.name) {
+            Some(existing) => {
+                let mut rule = rule.clone();
+                if rule.pattern.is_empty() {
+                    rule.pattern = existing.pattern.clone();
+                    rule.regex = existing.regex;
+                }
+                if rule.resolved.is_none() {
+                    rule.resolved = existing.resolved.clone();
+                }
+                *existing = rule;
+            }
+            None => ret.push(rule.clone()),
+        }
+    }
+    ret.retain_mut(|rule| {
+        if rule.pattern.is_empty() && rule.resolved.is_none() {
+            error!(
+                "Ignoring rule `{}` without a `pattern` or `resolved` name",
+                rule.name
+            );
+            return false;
+        }
+        if !rule.regex {
+            return true;
+        }
+        match Regex::new(&rule.pattern) {
+            Ok(re) => {
+                rule.compiled = Some(re);
+                true
+            }
+            Err(e) => {
+                error!("Ignoring rule `{}` with invalid regex: {e}", rule.name);
+                false
+            }
+        }
+    });
+    ret
+}
+
+impl Rule {
+    pub fn applies_to(&self, lang: &Language) -> bool {
+        self.enabled && (self.lang.is_empty() || self.lang.contains(lang))
+    }
+
+    /// The rule's own action, then the configured policy for its category, then `Block` for
+    /// errors, `Flag` for warnings and `List` for notes.
+    pub fn action(&self) -> Action {
+        self.action
+            .or_else(|| {
+                self.category
+                    .as_ref()
+                    .and_then(|category| CONFIG.policy.get(category).copied())
+            })
+            .unwrap_or(match self.severity {
+                Severity::Error => Action::Block,
+                Severity::Warning => Action::Flag,
+                Severity::Info => Action::List,
+            })
+    }
+
+    /// The compiled pattern of a regex rule.
+    pub fn regex(&self) -> Option<&Regex> {
+        self.compiled.as_ref()
+    }
+}
+
+/// Rules that apply to `lang` and are not turned off by allowing their category.
+pub fn active(lang: &Language, prohibited: &[Allow]) -> Vec<&'static Rule> {
+    RULESET
+        .iter()
+        .filter(|rule| rule.applies_to(lang))
+        .filter(|rule| {
+            rule.category
+                .as_ref()
+                .is_none_or(|category| prohibited.contains(category))
+        })
+        .collect()
+}
+
+/// Active rules for `lang` that name what they match with `resolved`.
+pub fn resolved(lang: &Language, prohibited: &[Allow]) -> Vec<&'static Rule> {
+    active(lang, prohibited)
+        .into_iter()
+        .filter(|rule| rule.resolved.is_some())
+        .collect()
+}
+
+/// Every resolved name for `lang`, whether or not its rule is active.
+pub fn resolved_names(lang: &Language) -> impl Iterator<Item = &'static str> {
+    RULESET
+        .iter()
+        .filter(|rule| rule.lang.is_empty() || rule.lang.contains(lang))
+        .filter_map(|rule| rule.resolved.as_deref())
+}
+
+/// Rules the `AST` checker matches on tokens in Java and Python sources. Categorized rules
+/// without a `resolved` name only approximate the resolved ones, so it leaves them out.
+pub fn unresolved(lang: &Language, prohibited: &[Allow]) -> Vec<&'static Rule> {
+    active(lang, prohibited)
+        .into_iter()
+        .filter(|rule| rule.resolved.is_none() && rule.category.is_none())
+        .collect()
+}
+
+/// Warns about configured rules the `AST` checker will skip in Java and Python sources.
+pub fn warn_unresolved(overrides: &[Rule]) {
+    let ast = [Language::Java, Language::Python];
+    for rule in overrides {
+        let inherited = BUILTIN
+            .iter()
+            .any(|el| el.name == rule.name && el.resolved.is_some());
+        if rule.enabled
+            && rule.category.is_some()
+            && rule.resolved.is_none()
+            && !inherited
+            && (rule.lang.is_empty() || rule.lang.iter().any(|lang| ast.contains(lang)))
+        {
+            warn!(
+                "Rule `{}` has a category but no `resolved` name; the AST checker ignores it \
+                 in Java and Python sources.",
+                rule.name
+            );
+        }
+    }
+}
+
+/// Action for a match of `category` that did not come from a rule, such as an AST pattern.
+pub fn category_action(category: &Allow) -> Action {
+    CONFIG
+        .policy
+        .get(category)
+        .copied()
+        .unwrap_or(Action::Block)
+}
@@ -0,0 +1,518 @@
+//! Style analysis of Java submissions: naming conventions, indentation, magic numbers, method
+//! length and braces. Like requirements, style never stops a submission from running; every
+//! finding deducts its rule's weight from a separate style score.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use strum_macros::AsRefStr;
+use walkdir::WalkDir;
+
+use super::lexer::{Pos, Token, TokenKind, tokenize_java};
+use super::require::{brace_methods, skip_balanced};
+use crate::config::CONFIG;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct StyleConfig {
+    /// Style score of a submission without findings.
+    pub points: u64,
+    /// Points deducted per finding of each rule; 1 for rules not listed, and 0 turns a rule off.
+    #[serde(default)]
+    pub weights: HashMap<StyleRule, u64>,
+    /// Columns per indentation level; a tab counts as one level.
+    #[serde(default = "default_indent")]
+    pub indent: usize,
+    /// Most lines of code a method body may have.
+    #[serde(default = "default_method_lines")]
+    pub max_method_lines: usize,
+    /// Numbers that may appear outside of constant declarations.
+    #[serde(default = "default_numbers")]
+    pub numbers: Vec<f64>,
+}
+
+const fn default_indent() -> usize {
+    4
+}
+
+const fn default_method_lines() -> usize {
+    40
+}
+
+fn default_numbers() -> Vec<f64> {
+    vec![0.0, 1.0, 2.0]
+}
+
+impl StyleConfig {
+    pub fn weight(&self, rule: StyleRule) -> u64 {
+        self.weights.get(&rule).copied().unwrap_or(1)
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, AsRefStr, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+#[non_exhaustive]
+pub enum StyleRule {
+    /// UpperCamelCase types, lowerCamelCase methods and variables, UPPER_SNAKE_CASE constants.
+    Naming,
+    /// Blocks indented one level deeper than the statement that opens them.
+    Indentation,
+    /// Numeric literals outside of `final` declarations.
+    MagicNumber,
+    /// Methods longer than `max_method_lines`.
+    MethodLength,
+    /// `if`, `else`, `for`, `while` and `do` without a block.
+    Braces,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct StyleFinding {
+    pub rule: StyleRule,
+    pub file: String,
+    /// One-based, like security findings.
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub points: u64,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct StyleReport {
+    pub points_awarded: u64,
+    pub max_points: u64,
+    pub findings: Vec<StyleFinding>,
+}
+
+/// A finding before it is attached to a file.
+type Found = (StyleRule, Pos, String);
+
+/// Checks the style of every submission in `dirs`. Submissions without Java sources are left
+/// out, so they are not awarded style points.
+pub fn check_dirs(dirs: Vec<PathBuf>, config: &StyleConfig) -> HashMap<PathBuf, StyleReport> {
+    let ret: HashMap<PathBuf, StyleReport> = dirs
+        .into_iter()
+        .filter_map(|dir| {
+            let report = check_dir(&dir, config)?;
+            Some((dir, report))
+        })
+        .collect();
+    info!(
+        "Style check complete: {} submission(s) with style findings.",
+        ret.values()
+            .filter(|report| !report.findings.is_empty())
+            .count()
+    );
+    ret
+}
+
+/// The style report of one submission, or `None` when it has no Java source to check.
+pub fn check_dir(dir: &Path, config: &StyleConfig) -> Option<StyleReport> {
+    let mut findings = vec![];
+    let mut checked = false;
+    for entry in WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+    {
+        let path = entry.path();
+        // the teacher's dependencies are not scored against the student.
+        if !path.is_file()
+            || path.extension().is_none_or(|ext| ext != "java")
+            || CONFIG.is_dependency(dir, path)
+        {
+            continue;
+        }
+        match read_to_string(path) {
+            Ok(src) => {
+                checked = true;
+                findings.extend(check_source(path, &src, config));
+            }
+            Err(e) => error!("Failed to read {}: {e}", path.display()),
+        }
+    }
+    if !checked {
+        debug!("{}: no Java sources to check the style of.", dir.display());
+        return None;
+    }
+    let deducted: u64 = findings.iter().map(|finding| finding.points).sum();
+    debug!(
+        "{}: {} style finding(s), -{deducted} pts.",
+        dir.display(),
+        findings.len()
+    );
+    Some(StyleReport {
+        points_awarded: config.points.saturating_sub(deducted),
+        max_points: config.points,
+        findings,
+    })
+}
+
+/// Style findings in one Java source file, in source order.
+pub fn check_source(path: &Path, src: &str, config: &StyleConfig) -> Vec<StyleFinding> {
+    let tokens = tokenize_java(src);
+    let mut found = naming(&tokens);
+    found.extend(indentation(src, &tokens, config.indent));
+    found.extend(magic_numbers(&tokens, &config.numbers));
+    found.extend(method_length(&tokens, config.max_method_lines));
+    found.extend(braces(&tokens));
+    found.sort_by_key(|(_, pos, _)| *pos);
+    found
+        .into_iter()
+        .filter(|(rule, ..)| config.weight(*rule) > 0)
+        .map(|(rule, (line, column), message)| StyleFinding {
+            rule,
+            file: path.display().to_string(),
+            line: line + 1,
+            column: column + 1,
+            message,
+            points: config.weight(rule),
+        })
+        .collect()
+}
+
+const TYPE_KEYWORDS: [&str; 4] = ["class", "interface", "enum", "record"];
+
+/// Words that can precede an identifier followed by `=`, `;`, `,`, `:` or `)` without it being
+/// declared there.
+const NOT_TYPES: [&str; 17] = [
+    "return",
+    "new",
+    "else",
+    "throw",
+    "throws",
+    "case",
+    "yield",
+    "package",
+    "import",
+    "break",
+    "continue",
+    "assert",
+    "do",
+    "default",
+    "extends",
+    "implements",
+    "permits",
+];
+
+fn is_upper_camel(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_uppercase) && name.chars().all(char::is_alphanumeric)
+}
+
+fn is_lower_camel(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_lowercase) && name.chars().all(char::is_alphanumeric)
+}
+
+fn is_constant(name: &str) -> bool {
+    name.chars().next().is_some_and(char::is_uppercase)
+        && name
+            .chars()
+            .all(|c| c.is_uppercase() || c.is_ascii_digit() || c == '_')
+        && !name.contains("__")
+        && !name.ends_with('_')
+}
+
+fn naming(tokens: &[Token]) -> Vec<Found> {
+    let mut ret = vec![];
+    let mut types = HashSet::new();
+    for pair in tokens.windows(2) {
+        let (keyword, name) = (&pair[0], &pair[1]);
+        if !TYPE_KEYWORDS.contains(&keyword.text.as_str()) || !name.is_ident() {
+            continue;
+        }
+        types.insert(name.text.as_str());
+        if !is_upper_camel(&name.text) {
+            ret.push((
+                StyleRule::Naming,
+                name.start,
+                format!("{} `{}` should be UpperCamelCase", keyword.text, name.text),
+            ));
+        }
+    }
+    for method in brace_methods(tokens) {
+        // constructors are named after their class.
+        if types.contains(method.name) || is_lower_camel(method.name) {
+            continue;
+        }
+        ret.push((
+            StyleRule::Naming,
+            method.start,
+            format!("method `{}` should be lowerCamelCase", method.name),
+        ));
+    }
+    for i in 1..tokens.len() {
+        let name = &tokens[i];
+        let ends = tokens
+            .get(i + 1)
+            .is_some_and(|next| ["=", ";", ",", ":", ")"].iter().any(|p| next.is(p)));
+        if !name.is_ident() || !ends || !declares(tokens, i) || name.is("_") {
+            continue;
+        }
+        if is_lower_camel(&name.text) || (is_final(tokens, i) && is_constant(&name.text)) {
+            continue;
+        }
+        let expected = if is_final(tokens, i) {
+            "lowerCamelCase or UPPER_SNAKE_CASE"
+        } else {
+            "lowerCamelCase"
+        };
+        ret.push((
+            StyleRule::Naming,
+            name.start,
+            format!("variable `{}` should be {expected}", name.text),
+        ));
+    }
+    ret
+}
+
+/// Whether the identifier at `i` follows a type, so it is the name of a variable, field or
+/// parameter.
+fn declares(tokens: &[Token], i: usize) -> bool {
+    let prev = &tokens[i - 1];
+    if prev.is_ident() {
+        return !NOT_TYPES.contains(&prev.text.as_str())
+            && !TYPE_KEYWORDS.contains(&prev.text.as_str())
+            && !prev.is("instanceof");
+    }
+    if prev.is("]") {
+        return tokens.get(i.wrapping_sub(2)).is_some_and(|t| t.is("["));
+    }
+    if !prev.is(">") {
+        return false;
+    }
+    // `List<String> names`, but not `a > b`.
+    let mut depth = 0usize;
+    for j in (0..i).rev() {
+        let token = &tokens[j];
+        if token.is(">") {
+            depth += 1;
+        } else if token.is("<") {
+            depth -= 1;
+            if depth == 0 {
+                return j > 0 && is_upper_camel(&tokens[j - 1].text);
+            }
+        } else if !(token.is_ident() || [",", ".", "?", "[", "]", "&"].iter().any(|p| token.is(p)))
+        {
+            return false;
+        }
+    }
+    false
+}
+
+/// Index of the first token of the statement containing `i`. Array initializers are part of
+/// their statement.
+fn statement_start(tokens: &[Token], i: usize) -> usize {
+    for j in (0..i).rev() {
+        let token = &tokens[j];
+        let initializer = |k: Option<usize>| {
+            k.and_then(|k| tokens.get(k))
+                .is_some_and(|t| ["=", "]", ",", "{"].iter().any(|p| t.is(p)))
+        };
+        if token.is(";")
+            || (token.is("{") && !initializer(j.checked_sub(1)))
+            || (token.is("}") && !tokens.get(j + 1).is_some_and(|t| t.is(",")))
+        {
+            return j + 1;
+        }
+    }
+    0
+}
+
+/// Whether the token at `i` is part of a `final` declaration.
+fn is_final(tokens: &[Token], i: usize) -> bool {
+    tokens[statement_start(tokens, i)..i]
+        .iter()
+        .any(|t| t.is("final"))
+}
+
+/// Value of a Java numeric literal.
+fn value(text: &str) -> Option<f64> {
+    let text = text.replace('_', "").to_ascii_lowercase();
+    let radix = |digits: &str, radix| {
+        i64::from_str_radix(digits.trim_end_matches('l'), radix)
+            .ok()
+            .map(|v| v as f64)
+    };
+    if let Some(hex) = text.strip_prefix("0x") {
+        return radix(hex, 16);
+    }
+    if let Some(bin) = text.strip_prefix("0b") {
+        return radix(bin, 2);
+    }
+    text.trim_end_matches(['l', 'f', 'd']).parse().ok()
+}
+
+fn magic_numbers(tokens: &[Token], allowed: &[f64]) -> Vec<Found> {
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| token.kind == TokenKind::Number)
+        .filter(|(_, token)| value(&token.text).is_none_or(|v| !allowed.contains(&v)))
+        .filter(|(i, _)| !is_final(tokens, *i))
+        .map(|(_, token)| {
+            (
+                StyleRule::MagicNumber,
+                token.start,
+                format!(
+                    "magic number `{}`; declare it as a named constant",
+                    token.text
+                ),
+            )
+        })
+        .collect()
+}
+
+fn method_length(tokens: &[Token], max: usize) -> Vec<Found> {
+    brace_methods(tokens)
+        .into_iter()
+        .filter_map(|method| {
+            let lines = method
+                .body
+                .iter()
+                .map(|t| t.start.0)
+                .collect::<HashSet<_>>()
+                .len();
+            (lines > max).then(|| {
+                (
+                    StyleRule::MethodLength,
+                    method.start,
+                    format!(
+                        "method `{}` has {lines} lines of code, at most {max} allowed",
+                        method.name
+                    ),
+                )
+            })
+        })
+        .collect()
+}
+
+fn braces(tokens: &[Token]) -> Vec<Found> {
+    let mut ret = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        let next = |j: usize| tokens.get(j);
+        let body = match token.text.as_str() {
+            "if" | "for" | "while" if next(i + 1).is_some_and(|t| t.is("(")) => {
+                skip_balanced(tokens, i + 1, "(", ")")
+            }
+            "else" if !next(i + 1).is_some_and(|t| t.is("if")) => i + 1,
+            "do" => i + 1,
+            _ => continue,
+        };
+        let Some(first) = next(body) else {
+            continue;
+        };
+        // the `while` of a `do` loop.
+        let do_while = token.is("while") && i > 0 && tokens[i - 1].is("}") && first.is(";");
+        if first.is("{") || do_while {
+            continue;
+        }
+        ret.push((
+            StyleRule::Braces,
+            token.start,
+            format!("`{}` without braces", token.text),
+        ));
+    }
+    ret
+}
+
+/// A block being indented: its contents go at `base` columns.
+struct Frame {
+    base: usize,
+    switch: bool,
+    /// Indentation of the last `case` label in a `switch`, whose statements go one level deeper.
+    label: Option<usize>,
+}
+
+/// Lines whose first token is not indented one level deeper than the statement opening their
+/// block. Continuation lines of a statement may be indented freely, and `case` labels may line
+/// up with their `switch`. Consecutive lines that are off by the same amount are one finding.
+fn indentation(src: &str, tokens: &[Token], indent: usize) -> Vec<Found> {
+    let lines: Vec<&str> = src.split('\n').collect();
+    let mut ret: Vec<Found> = vec![];
+    let mut frames = vec![Frame {
+        base: 0,
+        switch: false,
+        label: None,
+    }];
+    let mut statement = 0;
+    let mut pending_switch = false;
+    // how far the last finding's line was off, how many lines after it are off by as much, and
+    // its original message.
+    let mut run: Option<(isize, usize, String)> = None;
+    for (i, token) in tokens.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|j| &tokens[j]);
+        let leading: &str = lines.get(token.start.0).map_or("", |line| {
+            let end = line.len() - line.trim_start().len();
+            &line[..end]
+        });
+        let first = prev.is_none_or(|p| p.end.0 < token.start.0)
+            && leading.chars().count() == token.start.1;
+        let continuation = prev.is_some_and(|p| ![";", "{", "}", ":"].iter().any(|end| p.is(end)));
+        let closed = if token.is("}") && frames.len() > 1 {
+            frames.pop()
+        } else {
+            None
+        };
+        if first && !continuation {
+            let width: usize = leading
+                .chars()
+                .map(|c| if c == '\t' { indent } else { 1 })
+                .sum();
+            let frame = frames.last_mut().expect("the root frame is never popped");
+            let label = token.is("case")
+                || (token.is("default")
+                    && tokens.get(i + 1).is_some_and(|t| t.is(":") || t.is("-")));
+            let expected = if token.is("}") {
+                vec![closed.map_or(0, |block| block.base.saturating_sub(indent))]
+            } else if frame.switch && label {
+                frame.label = Some(width);
+                vec![frame.base, frame.base.saturating_sub(indent)]
+            } else if let (true, Some(label)) = (frame.switch, frame.label) {
+                vec![label + indent]
+            } else {
+                vec![frame.base]
+            };
+            if leading.contains(' ') && leading.contains('\t') {
+                run = None;
+                ret.push((
+                    StyleRule::Indentation,
+                    token.start,
+                    "indentation mixes tabs and spaces".to_owned(),
+                ));
+            } else if expected.contains(&width) {
+                run = None;
+            } else {
+                let off = width as isize - expected[0] as isize;
+                match (&mut run, ret.last_mut()) {
+                    (Some((offset, count, first)), Some((.., message))) if *offset == off => {
+                        *count += 1;
+                        let lines = if *count == 1 { "line is" } else { "lines are" };
+                        *message = format!("{first}; the next {count} {lines} off by as much");
+                    }
+                    _ => {
+                        let message = format!("indented {width} columns, expected {}", expected[0]);
+                        run = Some((off, 0, message.clone()));
+                        ret.push((StyleRule::Indentation, token.start, message));
+                    }
+                }
+            }
+            statement = width;
+        }
+        if token.is("switch") {
+            pending_switch = true;
+        } else if token.is("{") {
+            frames.push(Frame {
+                base: statement + indent,
+                switch: pending_switch,
+                label: None,
+            });
+            pending_switch = false;
+        }
+    }
+    ret
+}
@@ -1,6 +1,14 @@
+use crate::api::ApiRequirement;
+use crate::checker::require::Requirement;
+use crate::checker::rules::{Action, Rule};
+use crate::checker::static_check::Allow;
+use crate::checker::style::StyleConfig;
 use crate::checker::{self, Type};
 use crate::executable::Language;
+use crate::similarity::SimilarityConfig;
+use crate::stress::StressConfig;
 use crate::test::TestCase;
+use crate::unit::UnitConfig;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use core::fmt::{Display, Formatter};
@@ -16,7 +24,7 @@ use std::fs::File;
 use std::fs::create_dir_all;
 #[cfg(not(feature = "gui"))]
 use std::io::Read as _;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::sync::LazyLock;
 use std::thread::available_parallelism;
@@ -114,6 +122,7 @@ fn load_config() -> Config {
                     input: a.to_string(),
                     expected: b.to_string(),
                     points: *c,
+                    ..TestCase::default()
                 },
                 Left((a, b)) => {
                     debug!("Found test case without any points! Falling back to one point.");
@@ -121,6 +130,7 @@ fn load_config() -> Config {
                         input: a.to_string(),
                         expected: b.to_string(),
                         points: 1,
+                        ..TestCase::default()
                     }
                 }
                 Right(c) => {
@@ -129,10 +139,14 @@ fn load_config() -> Config {
                         input: String::new(),
                         expected: String::new(),
                         points: *c,
+                        ..TestCase::default()
                     }
                 }
             })
+            .chain(cp.cases.unwrap_or_default())
             .collect(),
+        env: cp.env.unwrap_or_default(),
+        inherit_env: cp.inherit_env.unwrap_or_default(),
         timeout: cp.timeout.unwrap_or(5),
         memory: cp.memory.unwrap_or(1024),
         threads: cp
@@ -143,7 +157,7 @@ fn load_config() -> Config {
                     .unwrap_or(4)
             })
             .max(1),
-        checker: cp.checker.unwrap_or(Type::Static),
+        checker: cp.checker.clone().unwrap_or(Type::Static),
         allow: cp.allow.unwrap_or_default(),
         format: cp.format.as_ref().map_or_else(
             || "{name}_{num}_{id}_{filename}.{extension}".into(),
@@ -151,6 +165,30 @@ fn load_config() -> Config {
         ),
         orderby: cp.orderby.unwrap_or(Orderby::Id),
         dependencies: cp.dependencies.unwrap_or_default(),
+        stress: cp.stress,
+        unit: cp.unit.map(|mut unit| {
+            unit.discover();
+            if cp.timeout == Some(0) {
+                error!("Unit tests need a nonzero `timeout`; every unit test will error.");
+            }
+            unit
+        }),
+        api: cp.api.unwrap_or_default(),
+        rules: cp
+            .rules
+            .inspect(|rules| {
+                if matches!(cp.checker, Some(Type::AST)) {
+                    checker::rules::warn_unresolved(rules);
+                }
+            })
+            .unwrap_or_default(),
+        require: cp.require.unwrap_or_default(),
+        policy: cp.policy.unwrap_or_default(),
+        imports: cp.imports,
+        bytecode: cp.bytecode.unwrap_or_default(),
+        style: cp.style,
+        similarity: cp.similarity,
+        starter: cp.starter.unwrap_or_default(),
     }
 }
 
@@ -244,6 +282,20 @@ pub struct ConfigParams {
     pub format: Option<String>,
     pub orderby: Option<Orderby>,
     pub dependencies: Option<Vec<PathBuf>>,
+    pub stress: Option<StressConfig>,
+    pub cases: Option<Vec<TestCase>>,
+    pub env: Option<HashMap<String, String>>,
+    pub inherit_env: Option<Vec<String>>,
+    pub unit: Option<UnitConfig>,
+    pub api: Option<Vec<ApiRequirement>>,
+    pub rules: Option<Vec<Rule>>,
+    pub require: Option<Vec<Requirement>>,
+    pub policy: Option<HashMap<Allow, Action>>,
+    pub imports: Option<Vec<String>>,
+    pub bytecode: Option<bool>,
+    pub style: Option<StyleConfig>,
+    pub similarity: Option<SimilarityConfig>,
+    pub starter: Option<Vec<PathBuf>>,
 }
 
 impl Default for ConfigParams {
@@ -264,6 +316,20 @@ impl Default for ConfigParams {
             allow: Some(vec![]),
             orderby: Some(Orderby::Name),
             dependencies: Some(vec![]),
+            stress: None,
+            cases: None,
+            env: Some(HashMap::new()),
+            inherit_env: Some(vec![]),
+            unit: None,
+            api: Some(vec![]),
+            rules: Some(vec![]),
+            require: Some(vec![]),
+            policy: Some(HashMap::new()),
+            imports: None,
+            bytecode: Some(false),
+            style: None,
+            similarity: None,
+            starter: Some(vec![]),
         }
     }
 }
@@ -276,6 +342,10 @@ pub struct Config {
     pub args: Vec<String>,
     pub target: PathBuf,
     pub testcases: Vec<TestCase>,
+    /// Environment variables set for every run.
+    pub env: HashMap<String, String>,
+    /// Names of grader environment variables passed through to submissions.
+    pub inherit_env: Vec<String>,
     pub timeout: u64,
     pub memory: u64,
     pub threads: u64,
@@ -284,6 +354,45 @@ pub struct Config {
     pub format: String,
     pub orderby: Orderby,
     pub dependencies: Vec<PathBuf>,
+    pub stress: Option<StressConfig>,
+    pub unit: Option<UnitConfig>,
+    /// Classes and members every submission must declare.
+    pub api: Vec<ApiRequirement>,
+    /// Static-analysis rules added to or replacing the built-in ones.
+    pub rules: Vec<Rule>,
+    /// Constructs every submission has to contain.
+    pub require: Vec<Requirement>,
+    /// What to do with submissions that use a prohibited category.
+    pub policy: HashMap<Allow, Action>,
+    /// Packages and classes Java submissions may use; anything is allowed when unset.
+    pub imports: Option<Vec<String>>,
+    /// Whether compiled Java classes are scanned for prohibited references before running.
+    pub bytecode: bool,
+    /// Style rules Java submissions are scored on, with the points they are worth.
+    pub style: Option<StyleConfig>,
+    /// Settings of the similarity check between submissions; not run when unset.
+    pub similarity: Option<SimilarityConfig>,
+    /// Starter code handed out with the assignment, as files or directories.
+    pub starter: Vec<PathBuf>,
+}
+
+impl Config {
+    /// Every graded case in report order: stdout cases first, then unit tests.
+    pub fn cases(&self) -> impl Iterator<Item = &TestCase> {
+        self.testcases
+            .iter()
+            .chain(self.unit.iter().flat_map(|unit| unit.cases.iter()))
+    }
+
+    /// Whether `path` is a copy of one of the `dependencies`, which are copied into the root of
+    /// every submission directory before it is compiled and are not the student's work.
+    pub fn is_dependency(&self, submission: &Path, path: &Path) -> bool {
+        path.parent() == Some(submission)
+            && self
+                .dependencies
+                .iter()
+                .any(|dep| dep.file_name().is_some() && dep.file_name() == path.file_name())
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -301,6 +410,8 @@ impl Default for Config {
             args: vec![],
             target: env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
             testcases: vec![],
+            env: HashMap::new(),
+            inherit_env: vec![],
             timeout: 10000,
             memory: 10,
             threads: 5,
@@ -309,6 +420,17 @@ impl Default for Config {
             format: "{name}_{num}_{id}_{filename}.{extension}".into(),
             orderby: Orderby::Id,
             dependencies: vec![],
+            stress: None,
+            unit: None,
+            api: vec![],
+            rules: vec![],
+            require: vec![],
+            policy: HashMap::new(),
+            imports: None,
+            bytecode: false,
+            style: None,
+            similarity: None,
+            starter: vec![],
         }
     }
 }
@@ -317,6 +439,7 @@ impl Display for Config {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         writeln!(f, "Language: {:?}", self.lang)?;
         writeln!(f, "Args: {:?}", self.args)?;
+        writeln!(f, "Env: {:?}", self.env)?;
         writeln!(f, "Target: {:?}", self.target)?;
         writeln!(f, "Test Cases: {:?}", self.testcases)?;
         writeln!(f, "Timeout: {:?}", self.timeout)?;
@@ -362,6 +485,7 @@ pub enum CommandType {
     Run,
     Test,
     Format,
+    Archive,
 }
 
 #[derive(Debug, Subcommand, Clone)]
@@ -392,16 +516,31 @@ pub enum Command {
         /// sort results before printing
         #[clap(long)]
         sort: bool,
+        /// SARIF file for the static-analysis findings
+        #[clap(long)]
+        findings: Option<PathBuf>,
     },
     /// test features
     Test,
     Format,
+    /// import older submission folders into the similarity archive
+    Archive {
+        /// configuration file naming the archive and assignment
+        #[clap(long)]
+        config: Option<PathBuf>,
+        /// term the submissions are archived as
+        #[clap(long)]
+        term: String,
+        /// folders of submissions, named like `target`, or with one directory per submission
+        #[clap(required = true)]
+        folders: Vec<PathBuf>,
+    },
 }
 
 impl Args {
     pub const fn get_config(&self) -> Option<&PathBuf> {
         match &self.command {
-            Command::Run { config, .. } => config.as_ref(),
+            Command::Run { config, .. } | Command::Archive { config, .. } => config.as_ref(),
             Command::Init | Command::Test | Command::Format => None,
         }
     }
@@ -423,6 +562,7 @@ impl Default for Args {
                 dry_run: false,
                 artifacts: false,
                 sort: false,
+                findings: None,
             },
         }
     }
@@ -456,6 +596,12 @@ pub struct SimpleOpts {
     pub artifacts: bool,
     /// sort results before printing
     pub sort: bool,
+    /// SARIF file for the static-analysis findings
+    pub findings: Option<PathBuf>,
+    /// term the imported submissions are archived as
+    pub term: Option<String>,
+    /// folders of submissions to import into the archive
+    pub folders: Vec<PathBuf>,
 }
 impl SimpleOpts {
     #[must_use]
@@ -483,6 +629,9 @@ impl Default for SimpleOpts {
             dry_run: true,
             artifacts: false,
             sort: false,
+            findings: None,
+            term: None,
+            folders: vec![],
         }
     }
 }
@@ -516,6 +665,7 @@ impl From<Args> for SimpleOpts {
                 dry_run,
                 artifacts,
                 sort,
+                findings,
             } => {
                 ret.mode = CommandType::Run;
                 ret.test = test;
@@ -579,6 +729,7 @@ impl From<Args> for SimpleOpts {
                 ret.dry_run = dry_run;
                 ret.artifacts = artifacts;
                 ret.sort = sort;
+                ret.findings = findings;
             }
             Command::Test => {
                 ret.mode = CommandType::Test;
@@ -586,6 +737,11 @@ impl From<Args> for SimpleOpts {
             Command::Format => {
                 ret.mode = CommandType::Format;
             }
+            Command::Archive { term, folders, .. } => {
+                ret.mode = CommandType::Archive;
+                ret.term = Some(term);
+                ret.folders = folders;
+            }
         }
         ret
     }
@@ -634,6 +790,7 @@ pub fn proc_args() {
                     match tmp.extension().and_then(|ext| ext.to_str()) {
                         Some("json") => debug!("Output format: JSON"),
                         Some("txt") => debug!("Output format: Plaintext"),
+                        Some("sarif") => debug!("Output format: SARIF"),
                         Some(ext) => {
                             error!("Unsupported output format: {ext}");
                             info!("falling back to stdout.");
@@ -648,7 +805,7 @@ pub fn proc_args() {
                 debug!("No output file or directory specified. falling back to stdout.");
             }
         }
-        Command::Test | Command::Format => {}
+        Command::Test | Command::Format | Command::Archive { .. } => {}
     }
 }
 
@@ -1,5 +1,5 @@
 use log::{info, warn};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use strum_macros::EnumIter;
 use walkdir::WalkDir;
@@ -69,7 +69,7 @@ fn contains_in_zip(p: &PathBuf, target: &str) -> ZipResult<bool> {
     Ok(false)
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq, EnumIter, Serialize)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, EnumIter, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum Language {
     Java,
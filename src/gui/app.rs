@@ -49,6 +49,20 @@ pub fn launch() {
                         .map(|el| PathBuf::from_str(&el).unwrap())
                         .collect(),
                 ),
+                stress: None,
+                cases: None,
+                env: None,
+                inherit_env: None,
+                unit: None,
+                api: None,
+                rules: None,
+                require: None,
+                policy: None,
+                imports: None,
+                bytecode: None,
+                style: None,
+                similarity: None,
+                starter: None,
             })
             .is_err()
         {
@@ -0,0 +1,196 @@
+import java.io.ByteArrayOutputStream;
+import java.io.IOException;
+import java.io.PrintStream;
+import java.lang.reflect.InvocationTargetException;
+import java.lang.reflect.Method;
+import java.lang.reflect.Modifier;
+import java.util.Arrays;
+import java.util.Comparator;
+import java.util.Objects;
+
+/**
+ * Test runner bundled with bestest.
+ *
+ * <p>Every zero-argument {@code void} method whose name starts with {@code test} in the classes
+ * named on the command line is run once, on its own thread, with an optional {@code --timeout=<ms>}
+ * per test, where 0 means no limit. Results are written to stdout, one line per test:
+ *
+ * <pre>@@BESTEST@@ &lt;nonce&gt; &lt;Class.method&gt; &lt;PASS|FAIL|ERROR&gt; &lt;exception&gt; &lt;message&gt;</pre>
+ *
+ * <p>Fields are separated by tabs; tabs, newlines and backslashes inside fields are escaped.
+ * Anything the code under test prints is captured and discarded so it cannot corrupt the report.
+ * The nonce is the first line of stdin, read before any test runs and kept out of fields and
+ * arguments, so code that writes to the real stdout anyway cannot forge a result.
+ */
+public final class BestestHarness {
+    private static final String MARKER = "@@BESTEST@@";
+
+    private BestestHarness() {}
+
+    public static void main(String[] args) throws Exception {
+        ClassLoader loader = BestestHarness.class.getClassLoader();
+        loader.setDefaultAssertionStatus(true);
+        PrintStream out = System.out;
+        String nonce = readLine();
+        long timeout = 0;
+        for (String name : args) {
+            if (name.startsWith("--timeout=")) {
+                timeout = Long.parseLong(name.substring("--timeout=".length()));
+                continue;
+            }
+            Class<?> cls;
+            try {
+                cls = Class.forName(name, true, loader);
+            } catch (Throwable t) {
+                report(out, nonce, name + ".<class>", "ERROR", t.getClass().getName(), String.valueOf(t.getMessage()));
+                continue;
+            }
+            Method[] methods = cls.getDeclaredMethods();
+            Arrays.sort(methods, Comparator.comparing(Method::getName));
+            for (Method method : methods) {
+                if (!method.getName().startsWith("test") || method.getParameterCount() != 0
+                        || method.getReturnType() != void.class) {
+                    continue;
+                }
+                String id = cls.getSimpleName() + "." + method.getName();
+                Throwable[] failure = new Throwable[1];
+                Thread worker = new Thread(() -> {
+                    try {
+                        invoke(cls, method);
+                    } catch (Throwable t) {
+                        failure[0] = t;
+                    }
+                });
+                worker.setDaemon(true);
+                System.setOut(new PrintStream(new ByteArrayOutputStream(), true));
+                worker.start();
+                if (timeout > 0) {
+                    worker.join(timeout);
+                } else {
+                    // no limit; bestest itself never passes 0.
+                    worker.join();
+                }
+                System.setOut(out);
+                if (worker.isAlive()) {
+                    report(out, nonce, id, "ERROR", "java.util.concurrent.TimeoutException", "timed out after " + timeout + "ms");
+                } else if (failure[0] == null) {
+                    report(out, nonce, id, "PASS", "", "");
+                } else {
+                    Throwable cause = failure[0];
+                    if (cause instanceof InvocationTargetException && cause.getCause() != null) {
+                        cause = cause.getCause();
+                    }
+                    String status = cause instanceof AssertionError ? "FAIL" : "ERROR";
+                    report(out, nonce, id, status, cause.getClass().getName(), describe(cause));
+                }
+            }
+        }
+        out.flush();
+        // tests that timed out may still be running; do not wait for them.
+        System.exit(0);
+    }
+
+    /** Reads one line of stdin byte by byte, leaving the rest of it to the code under test. */
+    private static String readLine() throws IOException {
+        ByteArrayOutputStream line = new ByteArrayOutputStream();
+        for (int b = System.in.read(); b != -1 && b != '\n'; b = System.in.read()) {
+            line.write(b);
+        }
+        return line.toString().trim();
+    }
+
+    private static void invoke(Class<?> cls, Method method) throws Exception {
+        method.setAccessible(true);
+        Object target = Modifier.isStatic(method.getModifiers()) ? null : newInstance(cls);
+        Method setUp = find(cls, "setUp");
+        Method tearDown = find(cls, "tearDown");
+        if (setUp != null) {
+            setUp.invoke(Modifier.isStatic(setUp.getModifiers()) ? null : target);
+        }
+        try {
+            method.invoke(target);
+        } finally {
+            if (tearDown != null) {
+                tearDown.invoke(Modifier.isStatic(tearDown.getModifiers()) ? null : target);
+            }
+        }
+    }
+
+    private static Object newInstance(Class<?> cls) throws Exception {
+        java.lang.reflect.Constructor<?> ctor = cls.getDeclaredConstructor();
+        ctor.setAccessible(true);
+        return ctor.newInstance();
+    }
+
+    private static Method find(Class<?> cls, String name) {
+        try {
+            Method method = cls.getDeclaredMethod(name);
+            method.setAccessible(true);
+            return method;
+        } catch (NoSuchMethodException e) {
+            return null;
+        }
+    }
+
+    private static String describe(Throwable t) {
+        String message = t.getMessage() == null ? "" : t.getMessage();
+        for (StackTraceElement frame : t.getStackTrace()) {
+            if (!frame.getClassName().startsWith("java.") && !frame.getClassName().startsWith("jdk.")
+                    && !frame.getClassName().equals(BestestHarness.class.getName())) {
+                return message + " (at " + frame.getFileName() + ":" + frame.getLineNumber() + ")";
+            }
+        }
+        return message;
+    }
+
+    private static void report(PrintStream out, String nonce, String id, String status, String exception,
+            String message) {
+        out.println(MARKER + "\t" + nonce + "\t" + escape(id) + "\t" + status + "\t" + escape(exception) + "\t" + escape(message));
+    }
+
+    private static String escape(String s) {
+        return s.replace("\\", "\\\\").replace("\t", "\\t").replace("\n", "\\n").replace("\r", "\\r");
+    }
+
+    public static void fail(String message) {
+        throw new AssertionError(message);
+    }
+
+    public static void assertTrue(boolean condition) {
+        assertTrue("expected true", condition);
+    }
+
+    public static void assertTrue(String message, boolean condition) {
+        if (!condition) {
+            fail(message);
+        }
+    }
+
+    public static void assertFalse(boolean condition) {
+        assertTrue("expected false", !condition);
+    }
+
+    public static void assertEquals(Object expected, Object actual) {
+        if (!Objects.equals(expected, actual)) {
+            fail("expected: <" + expected + "> but was: <" + actual + ">");
+        }
+    }
+
+    public static void assertEquals(long expected, long actual) {
+        if (expected != actual) {
+            fail("expected: <" + expected + "> but was: <" + actual + ">");
+        }
+    }
+
+    public static void assertEquals(double expected, double actual, double delta) {
+        if (Math.abs(expected - actual) > delta) {
+            fail("expected: <" + expected + "> but was: <" + actual + "> (delta " + delta + ")");
+        }
+    }
+
+    public static void assertArrayEquals(int[] expected, int[] actual) {
+        if (!Arrays.equals(expected, actual)) {
+            fail("expected: <" + Arrays.toString(expected) + "> but was: <" + Arrays.toString(actual) + ">");
+        }
+    }
+}
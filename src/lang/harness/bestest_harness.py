@@ -0,0 +1,148 @@
+"""Test runner bundled with bestest.
+
+Every ``unittest`` test in the modules named on the command line is run once, with an optional
+``--timeout=<ms>`` per test. Results are written to stdout, one line per test::
+
+    @@BESTEST@@ <nonce> <module.Class.method> <PASS|FAIL|ERROR> <exception> <message>
+
+Fields are separated by tabs; tabs, newlines and backslashes inside fields are escaped. Anything
+the code under test prints is captured and discarded so it cannot corrupt the report. The nonce is
+the first line of stdin, read before any test module is imported, so stray lines written to the
+real stdout are not taken as results. This is not a defense: the student's module runs in this
+interpreter and can patch the harness, ``unittest`` or read the nonce from this module's frames,
+so its results can be forged by the code under test.
+"""
+
+import contextlib
+import importlib
+import io
+import os
+import signal
+import sys
+import sysconfig
+import traceback
+import unittest
+
+MARKER = "@@BESTEST@@"
+STDLIB = os.path.realpath(sysconfig.get_paths()["stdlib"])
+
+
+class TestTimeout(BaseException):
+    """Raised inside a test that ran out of time; not an ``Exception`` so tests cannot swallow it."""
+
+
+class Result(unittest.TestResult):
+    """Keeps the first exception of a test instead of a formatted traceback."""
+
+    def __init__(self):
+        super().__init__()
+        self.outcome = None
+
+    def _record(self, status, err):
+        if self.outcome is None:
+            self.outcome = (status, err)
+
+    def addFailure(self, test, err):
+        self._record("FAIL", err)
+
+    def addError(self, test, err):
+        self._record("ERROR", err)
+
+    def addSubTest(self, test, subtest, err):
+        if err is not None:
+            failed = issubclass(err[0], test.failureException)
+            self._record("FAIL" if failed else "ERROR", err)
+
+    def addSkip(self, test, reason):
+        self.outcome = ("FAIL", (unittest.SkipTest, unittest.SkipTest(reason), None))
+
+    def addUnexpectedSuccess(self, test):
+        self._record("FAIL", (AssertionError, AssertionError("unexpected success"), None))
+
+
+def main(argv):
+    out = sys.stdout
+    nonce = sys.stdin.readline().strip()
+    timeout = 0
+    for name in argv:
+        if name.startswith("--timeout="):
+            timeout = int(name[len("--timeout="):])
+            continue
+        try:
+            with contextlib.redirect_stdout(io.StringIO()):
+                module = importlib.import_module(name)
+        except BaseException as e:
+            report(out, nonce, name + ".<module>", "ERROR", exception_name(e), describe(e))
+            continue
+        suite = unittest.defaultTestLoader.loadTestsFromModule(module)
+        for test in sorted(flatten(suite), key=lambda test: test.id()):
+            result = Result()
+            with contextlib.redirect_stdout(io.StringIO()):
+                arm(timeout)
+                try:
+                    # a one-test suite still runs setUpClass/setUpModule fixtures.
+                    unittest.TestSuite([test]).run(result)
+                except TestTimeout as e:
+                    # the timer fired outside of the test body, e.g. in tearDown bookkeeping.
+                    result._record("ERROR", (type(e), e, e.__traceback__))
+                finally:
+                    arm(0)
+            if result.outcome is None:
+                report(out, nonce, test.id(), "PASS", "", "")
+            else:
+                status, (_, exc, _) = result.outcome
+                report(out, nonce, test.id(), status, exception_name(exc), describe(exc))
+    out.flush()
+    # tests that timed out may have left threads behind; do not wait for them.
+    os._exit(0)
+
+
+def flatten(suite):
+    for test in suite:
+        if isinstance(test, unittest.TestSuite):
+            yield from flatten(test)
+        else:
+            yield test
+
+
+def arm(timeout):
+    if not hasattr(signal, "setitimer"):
+        return
+    if timeout > 0:
+        signal.signal(signal.SIGALRM, expire(timeout))
+    signal.setitimer(signal.ITIMER_REAL, timeout / 1000)
+
+
+def expire(timeout):
+    def handler(signum, frame):
+        raise TestTimeout("timed out after %dms" % timeout)
+
+    return handler
+
+
+def exception_name(exc):
+    cls = type(exc)
+    if cls.__module__ in ("builtins", "__main__"):
+        return cls.__qualname__
+    return cls.__module__ + "." + cls.__qualname__
+
+
+def describe(exc):
+    message = str(exc)
+    for frame in reversed(traceback.extract_tb(exc.__traceback__)):
+        path = os.path.realpath(frame.filename)
+        if not path.startswith(STDLIB) and path != os.path.realpath(__file__):
+            return "%s (at %s:%d)" % (message, os.path.basename(frame.filename), frame.lineno)
+    return message
+
+
+def report(out, nonce, id, status, exception, message):
+    out.write("\t".join([MARKER, nonce, escape(id), status, escape(exception), escape(message)]) + "\n")
+
+
+def escape(s):
+    return s.replace("\\", "\\\\").replace("\t", "\\t").replace("\n", "\\n").replace("\r", "\\r")
+
+
+if __name__ == "__main__":
+    main(sys.argv[1:])
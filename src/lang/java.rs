@@ -5,8 +5,10 @@ use log::{debug, warn};
 #[cfg(unix)]
 use nix::sys::signal::{Signal, kill};
 use std::{
+    collections::HashMap,
+    ffi::OsString,
     fs::create_dir_all,
-    path::PathBuf,
+    path::{Path, PathBuf},
     process::{ExitStatus, Stdio},
     sync::OnceLock,
     time::{Duration, Instant},
@@ -19,12 +21,39 @@ use tokio::{
 
 pub struct JavaRunner {
     start: Option<Instant>,
-    command: Command,
+    args: Vec<String>,
+    env: HashMap<String, String>,
     process: Option<Child>,
     venv: Option<PathBuf>,
     entry: PathBuf,
     deps: Vec<PathBuf>,
     exitcode: OnceLock<i32>,
+    /// Directory the program runs in; a copy of the venv, or the venv itself when unset.
+    workdir: Option<PathBuf>,
+}
+
+impl JavaRunner {
+    /// Arguments given to `java` before the program's own, resolved against `dir`.
+    fn launch(&self, dir: &Path) -> Vec<OsString> {
+        let is_jar = self
+            .entry
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("jar"));
+        if is_jar {
+            let relative = self
+                .venv
+                .as_ref()
+                .and_then(|venv| self.entry.strip_prefix(venv).ok())
+                .map_or_else(|| self.entry.clone(), |rel| dir.join(rel));
+            vec!["--jar".into(), relative.into()]
+        } else {
+            vec![
+                "-cp".into(),
+                dir.into(),
+                self.entry.file_stem().unwrap_or_default().to_owned(),
+            ]
+        }
+    }
 }
 
 #[async_trait]
@@ -73,9 +102,17 @@ impl Runner for JavaRunner {
                 .venv
                 .as_ref()
                 .ok_or_else(|| RunError::CE(None, "Virtual environment not prepared".into()))?;
+            // sources added through `add_dep` are compiled with the entry; javac finds the
+            // rest of the submission through the venv.
+            let sources = self
+                .deps
+                .iter()
+                .filter(|dep| dep.extension().is_some_and(|ext| ext == "java"))
+                .filter_map(|dep| dep.file_name().map(|name| venv.join(name)));
             let mut compiler = Command::new("javac")
                 .current_dir(venv)
                 .arg(&self.entry)
+                .args(sources)
                 .stderr(Stdio::piped())
                 .spawn()
                 .map_err(|e| RunError::CE(None, e.to_string()))?;
@@ -143,7 +180,6 @@ impl Runner for JavaRunner {
         Ok(buf)
     }
     async fn new_from_venv(venv: PathBuf, entry: PathBuf) -> Result<Self, Error> {
-        let mut ret;
         let ext = entry
             .extension()
             .and_then(|ext| ext.to_str())
@@ -151,54 +187,31 @@ impl Runner for JavaRunner {
         match ext {
             "java" => {
                 debug!("detected bare java file.");
-                ret = Self {
-                    start: None,
-                    command: Command::new("java"),
-                    process: None,
-                    venv: Some(venv.clone()),
-                    entry: entry.clone(),
-                    deps: vec![],
-                    exitcode: OnceLock::new(),
-                };
-                ret.command
-                    .arg("-cp")
-                    .arg(&venv)
-                    .arg(
-                        entry
-                            .file_stem()
-                            .ok_or_else(|| Error::new("Entry missing file stem"))?,
-                    )
-                    .stdin(Stdio::piped())
-                    .stderr(Stdio::piped())
-                    .stdout(Stdio::piped());
-            }
-            "jar" => {
-                debug!("detected java executable archive.");
-                ret = Self {
-                    start: None,
-                    command: Command::new("java"),
-                    process: None,
-                    venv: Some(venv),
-                    entry: entry.clone(),
-                    deps: vec![],
-                    exitcode: OnceLock::new(),
-                };
-                ret.command
-                    .arg("--jar")
-                    .arg(&entry)
-                    .stdin(Stdio::piped())
-                    .stdout(Stdio::piped());
+                entry
+                    .file_stem()
+                    .ok_or_else(|| Error::new("Entry missing file stem"))?;
             }
+            "jar" => debug!("detected java executable archive."),
             _ => {
                 return Err(Error::new("Unsupported Java artifact"));
             }
         }
-        Ok(ret)
+        Ok(Self {
+            start: None,
+            args: vec![],
+            env: HashMap::new(),
+            process: None,
+            venv: Some(venv),
+            entry,
+            deps: vec![],
+            exitcode: OnceLock::new(),
+            workdir: None,
+        })
     }
     async fn run(&mut self) -> Result<(), RunError> {
         let venv = self
             .venv
-            .as_ref()
+            .clone()
             .ok_or_else(|| RunError::CE(None, "Virtual environment not prepared".into()))?;
         let mut contains = false;
         let entries = venv
@@ -220,8 +233,16 @@ impl Runner for JavaRunner {
             debug!("Hasn't been compiled and prepared yet! Compiling...");
             self.prepare().await?;
         }
-        let child = self
-            .command
+        let dir = self.workdir.clone().unwrap_or(venv);
+        let child = Command::new("java")
+            .args(self.launch(&dir))
+            .args(&self.args)
+            .current_dir(&dir)
+            .env_clear()
+            .envs(&self.env)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdout(Stdio::piped())
             .spawn()
             .map_err(|e| RunError::RE(None, e.to_string()))?;
         self.process = Some(child);
@@ -276,4 +297,13 @@ impl Runner for JavaRunner {
             Err(io::Error::other("process is not running"))
         }
     }
+    async fn args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+    async fn envs(&mut self, env: HashMap<String, String>) {
+        self.env = env;
+    }
+    async fn workdir(&mut self, dir: Option<PathBuf>) {
+        self.workdir = dir;
+    }
 }
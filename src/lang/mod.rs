@@ -1,3 +1,4 @@
 pub mod java;
+pub mod python;
 pub mod runner;
 pub mod unknown;
@@ -0,0 +1,254 @@
+use super::runner::{Error, RunError, Runner};
+use crate::executable::Language;
+use async_trait::async_trait;
+use log::{debug, warn};
+#[cfg(unix)]
+use nix::sys::signal::{Signal, kill};
+use std::{
+    collections::HashMap,
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+use tokio::{
+    fs::copy,
+    io::{self, AsyncReadExt as _, AsyncWriteExt as _},
+    process::{Child, ChildStdout, Command},
+};
+
+/// Interpreter used for both byte-compiling and running submissions.
+const PYTHON: &str = "python3";
+
+pub struct PythonRunner {
+    start: Option<Instant>,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+    process: Option<Child>,
+    venv: Option<PathBuf>,
+    entry: PathBuf,
+    deps: Vec<PathBuf>,
+    exitcode: OnceLock<i32>,
+    /// Directory the program runs in; a copy of the venv, or the venv itself when unset.
+    workdir: Option<PathBuf>,
+}
+
+impl PythonRunner {
+    /// Script passed to the interpreter, resolved against `dir`.
+    fn script(&self, dir: &Path) -> PathBuf {
+        self.venv
+            .as_ref()
+            .and_then(|venv| self.entry.strip_prefix(venv).ok())
+            .map_or_else(|| self.entry.clone(), |rel| dir.join(rel))
+    }
+}
+
+#[async_trait]
+impl Runner for PythonRunner {
+    async fn add_dep(&mut self, p: PathBuf) -> Result<(), String> {
+        self.deps.push(p.clone());
+        let venv = self
+            .venv
+            .clone()
+            .ok_or_else(|| "Virtual environment is not initialized".to_string())?;
+        let file_name = p
+            .file_name()
+            .ok_or_else(|| "Dependency path missing file name".to_string())?;
+        let target = venv.join(file_name);
+        copy(p, target).await.map_err(|e| format!("{e}"))?;
+        Ok(())
+    }
+    async fn add_deps(&mut self, p: Vec<PathBuf>) -> Result<(), String> {
+        self.deps.extend(p.clone());
+        let venvdir = self
+            .venv
+            .clone()
+            .ok_or_else(|| "Virtual environment is not initialized".to_string())?;
+        create_dir_all(&venvdir).map_err(|e| format!("{e}"))?;
+        for i in p {
+            let file_name = i
+                .file_name()
+                .ok_or_else(|| "Dependency path missing file name".to_string())?;
+            let target = venvdir.join(file_name);
+            copy(i, target).await.map_err(|e| format!("{e}"))?;
+        }
+        Ok(())
+    }
+    async fn prepare(&mut self) -> Result<(), RunError> {
+        let venv = self
+            .venv
+            .as_ref()
+            .ok_or_else(|| RunError::CE(None, "Virtual environment not prepared".into()))?;
+        // byte-compiling catches syntax errors up front, the same way javac would.
+        let sources = self
+            .deps
+            .iter()
+            .filter(|dep| dep.extension().is_some_and(|ext| ext == "py"))
+            .filter_map(|dep| dep.file_name().map(|name| venv.join(name)));
+        let mut compiler = Command::new(PYTHON)
+            .current_dir(venv)
+            .args(["-m", "py_compile"])
+            .arg(&self.entry)
+            .args(sources)
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| RunError::CE(None, e.to_string()))?;
+        match compiler.wait().await {
+            Ok(s) => match s.code() {
+                Some(0) => Ok(()),
+                Some(code) => {
+                    let mut r = String::new();
+                    if let Some(stderr) = compiler.stderr.as_mut() {
+                        let _ = stderr.read_to_string(&mut r).await;
+                    }
+                    Err(RunError::CE(Some(code), r))
+                }
+                None => Err(RunError::CE(
+                    None,
+                    "py_compile terminated without exit code".into(),
+                )),
+            },
+            Err(e) => Err(RunError::CE(None, e.to_string())),
+        }
+    }
+    async fn stdin(&mut self, input: String) -> Result<(), String> {
+        match &mut self.process {
+            Some(s) => match s.stdin.as_mut() {
+                Some(stdin) => stdin
+                    .write_all(input.as_bytes())
+                    .await
+                    .map_err(|e| format!("{e}")),
+                None => Err("Process stdin is not available".into()),
+            },
+            None => Err("Process has not started yet!".into()),
+        }
+    }
+    async fn stdout(&mut self) -> Option<&mut ChildStdout> {
+        match &mut self.process {
+            Some(s) => match &mut s.stdout {
+                Some(t) => return Some(t),
+                None => return None,
+            },
+            None => return None,
+        };
+    }
+    async fn exitcode(&mut self) -> Result<Option<ExitStatus>, std::io::Error> {
+        if self.running().await {
+            if let Some(process) = self.process.as_mut() {
+                process.try_wait()
+            } else {
+                Ok(None)
+            }
+        } else {
+            Ok(None)
+        }
+    }
+    async fn read_all(&mut self) -> Result<String, String> {
+        let stdout = match &mut self.process {
+            Some(s) => match &mut s.stdout {
+                Some(t) => t,
+                None => return Err("Stdout is not open!".into()),
+            },
+            None => return Err("Process is not running!".into()),
+        };
+        let mut buf: String = String::new();
+        let _ = stdout.read_to_string(&mut buf).await;
+        Ok(buf)
+    }
+    async fn new_from_venv(venv: PathBuf, entry: PathBuf) -> Result<Self, Error> {
+        if entry.extension().is_none_or(|ext| ext != "py") {
+            return Err(Error::new("Unsupported Python artifact"));
+        }
+        debug!("detected python script.");
+        Ok(Self {
+            start: None,
+            args: vec![],
+            env: HashMap::new(),
+            process: None,
+            venv: Some(venv),
+            entry,
+            deps: vec![],
+            exitcode: OnceLock::new(),
+            workdir: None,
+        })
+    }
+    async fn run(&mut self) -> Result<(), RunError> {
+        let venv = self
+            .venv
+            .clone()
+            .ok_or_else(|| RunError::CE(None, "Virtual environment not prepared".into()))?;
+        let dir = self.workdir.clone().unwrap_or(venv);
+        let child = Command::new(PYTHON)
+            .arg(self.script(&dir))
+            .args(&self.args)
+            .current_dir(&dir)
+            .env_clear()
+            .envs(&self.env)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| RunError::RE(None, e.to_string()))?;
+        self.process = Some(child);
+        self.start = Some(Instant::now());
+        Ok(())
+    }
+    async fn running(&mut self) -> bool {
+        match &mut self.process {
+            Some(child) => match child.try_wait() {
+                Ok(Some(status)) => {
+                    if let Some(code) = status.code() {
+                        let _ = self.exitcode.set(code);
+                    }
+                    false
+                }
+                Ok(None) => true,
+                Err(e) => {
+                    warn!("Failed to poll python process: {e}");
+                    false
+                }
+            },
+            None => false,
+        }
+    }
+    async fn get_lang(&self) -> Language {
+        Language::Python
+    }
+    #[cfg(unix)]
+    async fn signal(&mut self, s: Signal) -> Result<(), String> {
+        #[cfg(unix)]
+        let pid = nix::unistd::Pid::from_raw(if let Some(c) = &self.process {
+            c.id()
+                .ok_or_else(|| "Process id is unavailable".to_string())? as i32
+        } else {
+            log::error!("tried to kill PID that does not exist!");
+            return Err("tried to kill PID that does not exist".into());
+        });
+        #[cfg(unix)]
+        if let Err(e) = kill(pid, s) {
+            log::error!("failed to kill PID {pid}! error: {e}");
+            return Err(e.to_string());
+        }
+        Ok(())
+    }
+    async fn runtime(&self) -> Result<Duration, ()> {
+        self.start.as_ref().map_or(Err(()), |s| Ok(s.elapsed()))
+    }
+    async fn wait(&mut self) -> io::Result<ExitStatus> {
+        if let Some(process) = self.process.as_mut() {
+            process.wait().await
+        } else {
+            Err(io::Error::other("process is not running"))
+        }
+    }
+    async fn args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+    async fn envs(&mut self, env: HashMap<String, String>) {
+        self.env = env;
+    }
+    async fn workdir(&mut self, dir: Option<PathBuf>) {
+        self.workdir = dir;
+    }
+}
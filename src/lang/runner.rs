@@ -1,10 +1,12 @@
 use super::java::JavaRunner;
+use super::python::PythonRunner;
 use crate::{config::CONFIG, executable::Language, unpacker::find_in_dir};
 use async_trait::async_trait;
 use log::{debug, error, warn};
 #[cfg(unix)]
 use nix::sys::signal::Signal;
 use std::{
+    collections::HashMap,
     fmt::{Display, Formatter},
     path::PathBuf,
     process::ExitStatus,
@@ -35,8 +37,11 @@ impl Display for Error {
 
 pub async fn from_dir(p: PathBuf, lang: Option<Language>) -> Option<Box<dyn Runner>> {
     //probe
-    if lang.is_some() && lang.unwrap() != Language::Java {
-        error!("Language other than java not yet implemented!");
+    if lang
+        .as_ref()
+        .is_some_and(|lang| !matches!(lang, Language::Java | Language::Python | Language::Guess))
+    {
+        error!("Languages other than Java and Python are not yet implemented!");
         return None;
     }
     for dep in &CONFIG.dependencies {
@@ -81,6 +86,9 @@ pub async fn from_dir(p: PathBuf, lang: Option<Language>) -> Option<Box<dyn Runn
     debug!("Finished probing. Entry point: {entry:?}");
     match entry.extension().unwrap().to_str().unwrap() {
         "java" => Some(Box::new(JavaRunner::new_from_venv(p, entry).await.unwrap())),
+        "py" => Some(Box::new(
+            PythonRunner::new_from_venv(p, entry).await.unwrap(),
+        )),
         ext => {
             error!("Unknown extension: {ext}");
             None
@@ -90,6 +98,9 @@ pub async fn from_dir(p: PathBuf, lang: Option<Language>) -> Option<Box<dyn Runn
 
 impl core::error::Error for Error {}
 
+/// Variables every submission sees, independent of the grader's own environment.
+pub const BASE_ENV: [(&str, &str); 3] = [("LANG", "C.UTF-8"), ("LC_ALL", "C.UTF-8"), ("TZ", "UTC")];
+
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum RunError {
@@ -116,4 +127,10 @@ pub trait Runner: Send + Sync {
     async fn add_dep(&mut self, p: PathBuf) -> Result<(), String>;
     async fn add_deps(&mut self, p: Vec<PathBuf>) -> Result<(), String>;
     async fn wait(&mut self) -> io::Result<ExitStatus>;
+    /// Sets the arguments passed to the program on the next `run`.
+    async fn args(&mut self, args: Vec<String>);
+    /// Replaces the program's environment on the next `run`; nothing is inherited.
+    async fn envs(&mut self, env: HashMap<String, String>);
+    /// Runs the program from `dir` instead of the venv. `dir` must be a copy of the venv.
+    async fn workdir(&mut self, dir: Option<PathBuf>);
 }
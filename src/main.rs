@@ -6,12 +6,13 @@ use log::LevelFilter;
 use log::{debug, error, info, trace, warn};
 use std::{
     collections::{HashMap, HashSet},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use tokio::{
     fs::{File, remove_dir_all},
     io::AsyncWriteExt as _,
 };
+pub mod api;
 pub mod checker;
 pub mod config;
 pub mod executable;
@@ -19,14 +20,19 @@ pub mod executable;
 pub mod gui;
 pub mod lang;
 mod report;
+pub mod similarity;
+pub mod starter;
+pub mod stress;
 pub mod test;
+pub mod unit;
 pub mod unpacker;
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use checker::{IllegalExpr, check_dirs};
 use config::{CONFIG, CommandType, ConfigParams, SIMPLEOPTS, TEMPDIR, proc_args};
 use report::{
-    RunReport, TotalsSummary, UnpackSummary, detect_output_format, serialize_report,
-    summarize_security, summarize_submissions,
+    RunReport, SecuritySummary, SubmissionExtras, TotalsSummary, UnpackSummary,
+    detect_output_format, security_by_submission, serialize_findings, serialize_report,
+    summarize_metrics, summarize_security, summarize_submissions,
 };
 
 #[tokio::main]
@@ -71,6 +77,7 @@ async fn main() -> Result<()> {
             return Ok(());
         }
         CommandType::Run => run().await,
+        CommandType::Archive => archive().await,
         CommandType::Test | CommandType::Format => {
             todo!("Test and format are not yet implemented!")
         }
@@ -107,12 +114,9 @@ async fn run() -> Result<()> {
     }
     info!("Starting safety checks...");
     debug!("checking: {:?}", unpacked);
-    let check_result: HashMap<PathBuf, Vec<IllegalExpr>> = check_dirs(unpacked.clone())
-        .await?
-        .iter()
-        .filter(|el| !el.1.is_empty())
-        .map(|el| (el.0.clone(), el.1.clone()))
-        .collect();
+    let (checked, measured) = check_dirs(unpacked.clone()).await?;
+    let mut check_result: HashMap<PathBuf, Vec<IllegalExpr>> =
+        checked.into_iter().filter(|el| !el.1.is_empty()).collect();
     if check_result.is_empty() {
         info!("{} All safety checks passed.", style("[AC]").green().bold());
     } else {
@@ -120,13 +124,16 @@ async fn run() -> Result<()> {
         for i in &check_result {
             warn!("{i:?}");
         }
-        warn!("Aborting check for those files.");
+        warn!("Aborting check for submissions whose findings block them.");
         info!(
             "NOTE: if you want to allow potentially dangerous operations, configure it in config.toml."
         );
     }
-    let security_summary = summarize_security(&check_result);
-    let flagged_paths: Vec<PathBuf> = check_result.keys().cloned().collect();
+    let blocked: Vec<PathBuf> = security_by_submission(&check_result, submission_dir)
+        .iter()
+        .filter(|(_, verdict)| verdict.blocked)
+        .map(|(path, _)| path.clone())
+        .collect();
     // get the executables and remove dangerous files.
     let mut exec: HashSet<PathBuf> = HashSet::new();
     for entry in TEMPDIR
@@ -136,27 +143,99 @@ async fn run() -> Result<()> {
         let dir_entry = entry.context("failed to iterate temporary directory entry")?;
         exec.insert(dir_entry.path());
     }
-    for path in flagged_paths {
-        let mut rem = path.clone();
-        while let Some(parent) = rem.parent() {
-            if parent == TEMPDIR.as_path() {
-                break;
-            }
-            rem = parent.to_path_buf();
-        }
-        exec.remove(&rem);
+    for path in &blocked {
+        exec.remove(path);
     }
-    let total_points_available: u64 = config.testcases.iter().map(|tc| tc.points).sum();
+    let total_points_available: u64 = config.cases().map(|tc| tc.max_points()).sum::<u64>()
+        + config.api.iter().map(|req| req.points).sum::<u64>()
+        + config.style.as_ref().map_or(0, |style| style.points);
     info!("Starting tests...");
     debug!("Target dirs: {exec:?}");
     if exec.is_empty() {
         error!("None passed the safety test. Did you configure your safety settings correctly?");
+        // the findings are all there is to report, and they matter most here.
+        let security = security_by_submission(&check_result, submission_dir);
+        write_findings(&summarize_security(&check_result, &security)).await?;
         return Ok(());
     }
-    let res = test::test_dirs(exec).await?;
+    let (mut res, bytecode_findings) = test::test_dirs(exec).await?;
     debug!("Results: {res:#?}");
+    if !bytecode_findings.is_empty() {
+        warn!("Prohibited references found in compiled classes.");
+    }
+    check_result.extend(bytecode_findings);
+    let security = security_by_submission(&check_result, submission_dir);
+    let security_summary = summarize_security(&check_result, &security);
+    let mut extras: HashMap<PathBuf, SubmissionExtras> = HashMap::new();
+    // submissions the compiled-class scan blocked are in `res` without cases; nothing may run them.
+    let mut never_run = HashSet::new();
+    for (path, verdict) in security {
+        if verdict.blocked {
+            never_run.insert(path.clone());
+        }
+        extras.entry(path).or_default().security = Some(verdict);
+    }
+    let starter = starter::Starter::load(&config.starter);
+    if !config.starter.is_empty() {
+        for path in &unpacked {
+            extras.entry(path.clone()).or_default().starter = Some(starter.changes(path));
+        }
+    }
+    let metrics = checker::metrics::by_submission(&measured, submission_dir);
+    let metrics_summary = summarize_metrics(&metrics);
+    for (path, found) in metrics {
+        extras.entry(path).or_default().metrics = Some(found);
+    }
+    if !config.api.is_empty() {
+        info!("Checking required API...");
+        match api::check_dirs(res.iter().map(|(path, _)| path.clone()).collect()).await {
+            Ok(found) => {
+                for (path, report) in found {
+                    extras.entry(path).or_default().api = Some(report);
+                }
+            }
+            Err(e) => error!("API check failed: {e:#}"),
+        }
+    }
+    if !config.require.is_empty() {
+        info!("Checking required constructs...");
+        for (path, report) in
+            checker::require::check_dirs(res.iter().map(|(path, _)| path.clone()).collect())
+        {
+            extras.entry(path).or_default().require = Some(report);
+        }
+    }
+    if let Some(style) = &config.style {
+        info!("Checking style...");
+        for (path, report) in
+            checker::style::check_dirs(res.iter().map(|(path, _)| path.clone()).collect(), style)
+        {
+            extras.entry(path).or_default().style = Some(report);
+        }
+    }
+    if let Some(stress_cfg) = &config.stress {
+        info!("Starting stress tests...");
+        let targets = res
+            .iter()
+            .filter(|(path, results)| {
+                !never_run.contains(path)
+                    && (!stress_cfg.only_failing || results.iter().any(|case| !case.is_correct()))
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        match stress::stress_dirs(targets, stress_cfg).await {
+            Ok(found) => {
+                for (path, counterexample) in found {
+                    extras.entry(path).or_default().counterexample = Some(counterexample);
+                }
+            }
+            Err(e) => error!("Stress testing failed: {e:#}"),
+        }
+    }
+    // blocked submissions are reported with no cases run.
+    res.extend(blocked.into_iter().map(|path| (path, vec![])));
     let (mut submission_reports, mut scoreboard, test_totals) =
-        summarize_submissions(res, config, total_points_available);
+        summarize_submissions(res, config, total_points_available, extras);
     if SIMPLEOPTS.sort {
         submission_reports.sort_by(|a, b| a.name.cmp(&b.name));
         scoreboard.sort_by(|a, b| a.0.cmp(&b.0));
@@ -168,7 +247,12 @@ async fn run() -> Result<()> {
         max_points_per_submission: total_points_available,
         cases_total: test_totals.total_cases,
         cases_passed: test_totals.passed_cases,
+        metrics: metrics_summary,
     };
+    let similarity = config.similarity.as_ref().map(|similarity_cfg| {
+        info!("Comparing submissions...");
+        similarity::compare_dirs(&unpacked, similarity_cfg, &starter)
+    });
     let run_report = RunReport {
         unpack: UnpackSummary {
             prepared: unpacked.len(),
@@ -177,6 +261,7 @@ async fn run() -> Result<()> {
         },
         totals: totals_summary,
         security: security_summary,
+        similarity,
         submissions: submission_reports,
     };
     info!(
@@ -213,6 +298,7 @@ async fn run() -> Result<()> {
             println!("{name}: {score}");
         }
     }
+    write_findings(&run_report.security).await?;
     #[cfg(not(feature = "gui"))]
     if !SIMPLEOPTS.artifacts {
         debug!("cleaning up...");
@@ -226,3 +312,72 @@ async fn run() -> Result<()> {
 
     Ok(())
 }
+
+/// Writes the findings to the `--findings` SARIF file, if one was given.
+async fn write_findings(security: &SecuritySummary) -> Result<()> {
+    let Some(path) = SIMPLEOPTS.findings.clone() else {
+        return Ok(());
+    };
+    let payload = serialize_findings(security).context("failed to serialize findings")?;
+    File::create(&path)
+        .await
+        .with_context(|| format!("failed to create {}", path.display()))?
+        .write_all(&payload)
+        .await
+        .context("failed to write findings")?;
+    info!("Findings written to {}", path.display());
+    Ok(())
+}
+
+/// Imports the submission folders given on the command line into the similarity archive.
+async fn archive() -> Result<()> {
+    let Some(similarity_cfg) = &CONFIG.similarity else {
+        bail!("the config has no `[similarity]` table naming the archive");
+    };
+    let term = SIMPLEOPTS.term.clone().unwrap_or_default();
+    let mut dirs = vec![];
+    for folder in &SIMPLEOPTS.folders {
+        // submissions named like `target`, unpacked into TEMPDIR.
+        dirs.extend(
+            unpacker::unpack_dir(folder.clone())
+                .await
+                .into_iter()
+                .filter_map(Result::ok),
+        );
+        // submissions that are directories already.
+        if !folder.is_dir() {
+            continue;
+        }
+        let entries = std::fs::read_dir(folder)
+            .with_context(|| format!("failed to read {}", folder.display()))?;
+        for entry in entries {
+            let path = entry
+                .with_context(|| format!("failed to read an entry of {}", folder.display()))?
+                .path();
+            if path.is_dir() {
+                dirs.push(path);
+            }
+        }
+    }
+    dirs.sort();
+    dirs.dedup();
+    let count = similarity::archive::import(&dirs, similarity_cfg, &term)?;
+    info!("Archived {count} submission(s) as {term}.");
+    if TEMPDIR.exists() && !SIMPLEOPTS.artifacts {
+        debug!("cleaning up...");
+        remove_dir_all(TEMPDIR.clone()).await?;
+    }
+    Ok(())
+}
+
+/// The submission directory directly under `TEMPDIR` that `path` belongs to.
+fn submission_dir(path: &Path) -> PathBuf {
+    let mut rem = path.to_path_buf();
+    while let Some(parent) = rem.parent() {
+        if parent == TEMPDIR.as_path() {
+            break;
+        }
+        rem = parent.to_path_buf();
+    }
+    rem
+}
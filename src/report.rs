@@ -1,17 +1,34 @@
-use crate::{checker::IllegalExpr, config::Config, test::TestResult};
+use crate::{
+    api::{ApiProblem, ApiReport},
+    checker::{
+        IllegalExpr,
+        metrics::Metrics,
+        require::RequirementReport,
+        rules::{Action, Severity},
+        style::StyleReport,
+    },
+    config::Config,
+    similarity::SimilarityReport,
+    starter::StarterChanges,
+    stress::Counterexample,
+    test::{ExitVerdict, FileOutcome, FileVerdict, TestResult},
+};
 use anyhow::Result;
 use serde::Serialize;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::Write as FmtWrite,
     path::{Path, PathBuf},
 };
 
+pub mod sarif;
+
 #[derive(Serialize)]
 pub struct RunReport {
     pub unpack: UnpackSummary,
     pub totals: TotalsSummary,
     pub security: SecuritySummary,
+    pub similarity: Option<SimilarityReport>,
     pub submissions: Vec<SubmissionReport>,
 }
 
@@ -30,11 +47,35 @@ pub struct TotalsSummary {
     pub max_points_per_submission: u64,
     pub cases_total: usize,
     pub cases_passed: usize,
+    pub metrics: MetricsSummary,
+}
+
+/// Smallest, median and largest value of a metric across submissions.
+#[derive(Serialize, Default)]
+pub struct Spread<T> {
+    pub min: T,
+    pub median: T,
+    pub max: T,
+}
+
+#[derive(Serialize, Default)]
+pub struct MetricsSummary {
+    pub lines: Spread<usize>,
+    pub comment_ratio: Spread<f64>,
+    pub methods: Spread<usize>,
+    pub max_nesting: Spread<usize>,
+    pub max_complexity: Spread<usize>,
 }
 
 #[derive(Serialize)]
 pub struct SecuritySummary {
     pub flagged_files: usize,
+    /// Submissions that were not run.
+    pub blocked_submissions: usize,
+    /// Submissions that ran but are marked for review.
+    pub flagged_submissions: usize,
+    /// Submissions that ran with a point penalty.
+    pub penalized_submissions: usize,
     pub findings: Vec<SecurityFinding>,
 }
 
@@ -48,10 +89,26 @@ pub struct SecurityFinding {
 pub struct SecurityIssue {
     pub line: usize,
     pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
     pub violation: Option<String>,
+    pub rule: Option<String>,
+    pub severity: Severity,
+    pub action: Action,
+    pub message: Option<String>,
     pub snippet: Option<String>,
 }
 
+/// What the static checks did to one submission.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SubmissionSecurity {
+    pub blocked: bool,
+    pub flagged: bool,
+    pub points_deducted: u64,
+    /// Findings across all files of the submission.
+    pub issues: usize,
+}
+
 #[derive(Serialize)]
 pub struct SubmissionReport {
     pub name: String,
@@ -59,23 +116,45 @@ pub struct SubmissionReport {
     pub points_awarded: u64,
     pub max_points: u64,
     pub cases: Vec<CaseReport>,
+    pub counterexample: Option<Counterexample>,
+    pub api: Option<ApiReport>,
+    pub require: Option<RequirementReport>,
+    pub style: Option<StyleReport>,
+    pub security: Option<SubmissionSecurity>,
+    pub metrics: Option<Metrics>,
+    pub starter: Option<StarterChanges>,
 }
 
 #[derive(Serialize)]
 pub struct CaseReport {
     pub index: usize,
+    pub name: Option<String>,
     pub input: String,
     pub expected: String,
     pub points: u64,
     pub outcome: CaseOutcome,
+    pub exit: Option<ExitVerdict>,
+    pub files: Vec<FileVerdict>,
 }
 
 #[derive(Serialize)]
 #[serde(tag = "status", rename_all = "snake_case")]
 pub enum CaseOutcome {
-    Correct { output: String },
-    Wrong { output: String, diff: DiffSummary },
-    Error { code: i32, reason: String },
+    Correct {
+        output: String,
+    },
+    Wrong {
+        output: String,
+        diff: DiffSummary,
+    },
+    Error {
+        code: i32,
+        reason: String,
+    },
+    Failed {
+        exception: Option<String>,
+        message: String,
+    },
 }
 
 #[derive(Serialize)]
@@ -89,6 +168,20 @@ pub enum OutputFormat {
     Json,
     Toml,
     Plaintext,
+    /// Only the static-analysis findings, as SARIF 2.1.0.
+    Sarif,
+}
+
+/// Per-submission results gathered outside of the regular test cases.
+#[derive(Debug, Default)]
+pub struct SubmissionExtras {
+    pub counterexample: Option<Counterexample>,
+    pub api: Option<ApiReport>,
+    pub require: Option<RequirementReport>,
+    pub style: Option<StyleReport>,
+    pub security: Option<SubmissionSecurity>,
+    pub metrics: Option<Metrics>,
+    pub starter: Option<StarterChanges>,
 }
 
 #[derive(Debug, Default)]
@@ -99,7 +192,38 @@ pub struct TestTotals {
     pub perfect_scores: usize,
 }
 
-pub fn summarize_security(results: &HashMap<PathBuf, Vec<IllegalExpr>>) -> SecuritySummary {
+/// Combines the actions of every finding in a submission, where `submission_of` maps a file to
+/// its submission. `Deduct` counts once per rule and submission.
+pub fn security_by_submission(
+    results: &HashMap<PathBuf, Vec<IllegalExpr>>,
+    submission_of: impl Fn(&Path) -> PathBuf,
+) -> HashMap<PathBuf, SubmissionSecurity> {
+    let mut ret: HashMap<PathBuf, SubmissionSecurity> = HashMap::new();
+    let mut deducted: HashSet<(PathBuf, Option<String>)> = HashSet::new();
+    for (path, issues) in results {
+        let submission = submission_of(path);
+        let security = ret.entry(submission.clone()).or_default();
+        security.issues += issues.len();
+        for issue in issues {
+            match issue.action {
+                Action::Block => security.blocked = true,
+                Action::Flag => security.flagged = true,
+                Action::Deduct(points) => {
+                    if deducted.insert((submission.clone(), issue.rule.clone())) {
+                        security.points_deducted += points;
+                    }
+                }
+                Action::List => {}
+            }
+        }
+    }
+    ret
+}
+
+pub fn summarize_security(
+    results: &HashMap<PathBuf, Vec<IllegalExpr>>,
+    submissions: &HashMap<PathBuf, SubmissionSecurity>,
+) -> SecuritySummary {
     let findings = results
         .iter()
         .map(|(path, issues)| SecurityFinding {
@@ -109,25 +233,60 @@ pub fn summarize_security(results: &HashMap<PathBuf, Vec<IllegalExpr>>) -> Secur
                 .map(|issue| SecurityIssue {
                     line: issue.loc.1.saturating_add(1),
                     column: issue.loc.0.saturating_add(1),
+                    end_line: issue.end.1.saturating_add(1),
+                    end_column: issue.end.0.saturating_add(1),
                     violation: issue
                         .violates
                         .as_ref()
                         .map(|rule| rule.as_ref().to_string()),
+                    rule: issue.rule.clone(),
+                    severity: issue.severity,
+                    action: issue.action,
+                    message: issue.message.clone(),
                     snippet: issue.content.clone(),
                 })
                 .collect(),
         })
         .collect::<Vec<_>>();
+    let count =
+        |pred: fn(&SubmissionSecurity) -> bool| submissions.values().filter(|s| pred(s)).count();
     SecuritySummary {
         flagged_files: findings.len(),
+        blocked_submissions: count(|s| s.blocked),
+        flagged_submissions: count(|s| !s.blocked && s.flagged),
+        penalized_submissions: count(|s| !s.blocked && s.points_deducted > 0),
         findings,
     }
 }
 
+fn spread<T: Copy + Default + PartialOrd>(mut values: Vec<T>) -> Spread<T> {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    match (values.first(), values.last()) {
+        (Some(&min), Some(&max)) => Spread {
+            min,
+            median: values[values.len() / 2],
+            max,
+        },
+        _ => Spread::default(),
+    }
+}
+
+pub fn summarize_metrics(metrics: &HashMap<PathBuf, Metrics>) -> MetricsSummary {
+    let of = |field: fn(&Metrics) -> usize| spread(metrics.values().map(field).collect());
+    MetricsSummary {
+        lines: of(|m| m.lines),
+        comment_ratio: spread(metrics.values().map(|m| m.comment_ratio).collect()),
+        methods: of(|m| m.methods),
+        max_nesting: of(|m| m.max_nesting),
+        max_complexity: of(|m| m.max_complexity),
+    }
+}
+
 pub fn summarize_submissions(
     results: Vec<(PathBuf, Vec<TestResult>)>,
     config: &Config,
     max_points_per_submission: u64,
+    mut extras: HashMap<PathBuf, SubmissionExtras>,
 ) -> (Vec<SubmissionReport>, Vec<(String, u64)>, TestTotals) {
     let mut submissions = Vec::new();
     let mut scoreboard = Vec::new();
@@ -139,20 +298,36 @@ pub fn summarize_submissions(
         for (idx, result) in test_results.into_iter().enumerate() {
             totals.total_cases += 1;
             match result {
-                TestResult::Correct { case, output } => {
-                    totals.passed_cases += 1;
-                    submission_points += case.points;
+                TestResult::Correct {
+                    case,
+                    output,
+                    exit,
+                    files,
+                } => {
+                    totals.passed_cases += usize::from(exit.as_ref().is_none_or(|v| v.passed));
+                    submission_points += case.points + exit_points(exit.as_ref());
                     cases.push(CaseReport {
                         index: idx,
+                        name: case.name.clone(),
                         input: case.input.clone(),
                         expected: case.expected.clone(),
                         points: case.points,
                         outcome: CaseOutcome::Correct { output },
+                        exit,
+                        files,
                     });
                 }
-                TestResult::Wrong { case, output, diff } => {
+                TestResult::Wrong {
+                    case,
+                    output,
+                    diff,
+                    exit,
+                    files,
+                } => {
+                    submission_points += exit_points(exit.as_ref());
                     cases.push(CaseReport {
                         index: idx,
+                        name: case.name.clone(),
                         input: case.input.clone(),
                         expected: case.expected.clone(),
                         points: case.points,
@@ -163,20 +338,40 @@ pub fn summarize_submissions(
                                 removals: diff.count_removals() as usize,
                             },
                         },
+                        exit,
+                        files,
+                    });
+                }
+                TestResult::Failed {
+                    case,
+                    exception,
+                    message,
+                } => {
+                    cases.push(CaseReport {
+                        index: idx,
+                        name: case.name.clone(),
+                        input: case.input.clone(),
+                        expected: case.expected.clone(),
+                        points: case.points,
+                        outcome: CaseOutcome::Failed { exception, message },
+                        exit: None,
+                        files: vec![],
                     });
                 }
                 TestResult::Error { code, reason } => {
-                    let (input, expected, points) = config
-                        .testcases
-                        .get(idx)
+                    let case = config.cases().nth(idx);
+                    let (input, expected, points) = case
                         .map(|case| (case.input.clone(), case.expected.clone(), case.points))
                         .unwrap_or_else(|| (String::new(), String::new(), 0));
                     cases.push(CaseReport {
                         index: idx,
+                        name: case.and_then(|case| case.name.clone()),
                         input,
                         expected,
                         points,
                         outcome: CaseOutcome::Error { code, reason },
+                        exit: None,
+                        files: vec![],
                     });
                 }
             }
@@ -185,6 +380,21 @@ pub fn summarize_submissions(
             Some(name) => name.to_owned(),
             None => path.display().to_string(),
         };
+        let extra = extras.remove(&path).unwrap_or_default();
+        submission_points += extra.api.as_ref().map_or(0, |api| api.points_awarded);
+        submission_points += extra.style.as_ref().map_or(0, |style| style.points_awarded);
+        submission_points = submission_points.saturating_sub(
+            extra
+                .require
+                .as_ref()
+                .map_or(0, |require| require.points_deducted),
+        );
+        submission_points = submission_points.saturating_sub(
+            extra
+                .security
+                .as_ref()
+                .map_or(0, |security| security.points_deducted),
+        );
         scoreboard.push((name.clone(), submission_points));
         submissions.push(SubmissionReport {
             name,
@@ -192,6 +402,13 @@ pub fn summarize_submissions(
             points_awarded: submission_points,
             max_points: max_points_per_submission,
             cases,
+            counterexample: extra.counterexample,
+            api: extra.api,
+            require: extra.require,
+            style: extra.style,
+            security: extra.security,
+            metrics: extra.metrics,
+            starter: extra.starter,
         });
     }
 
@@ -207,6 +424,10 @@ pub fn summarize_submissions(
     (submissions, scoreboard, totals)
 }
 
+fn exit_points(verdict: Option<&ExitVerdict>) -> u64 {
+    verdict.filter(|v| v.passed).map_or(0, |v| v.points)
+}
+
 pub fn detect_output_format(path: &Path) -> (OutputFormat, bool) {
     let ext = path
         .extension()
@@ -215,6 +436,7 @@ pub fn detect_output_format(path: &Path) -> (OutputFormat, bool) {
     match ext.as_deref() {
         Some("json") => (OutputFormat::Json, true),
         Some("toml") => (OutputFormat::Toml, true),
+        Some("sarif") => (OutputFormat::Sarif, true),
         Some("txt") | None => (OutputFormat::Plaintext, true),
         _ => (OutputFormat::Plaintext, false),
     }
@@ -225,9 +447,15 @@ pub fn serialize_report(report: &RunReport, format: OutputFormat) -> Result<Vec<
         OutputFormat::Json => Ok(serde_json::to_vec_pretty(report)?),
         OutputFormat::Toml => Ok(toml::to_string_pretty(report)?.into_bytes()),
         OutputFormat::Plaintext => Ok(render_plain(report).into_bytes()),
+        OutputFormat::Sarif => serialize_findings(&report.security),
     }
 }
 
+/// The static-analysis findings alone, as SARIF.
+pub fn serialize_findings(security: &SecuritySummary) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec_pretty(&sarif::from_summary(security))?)
+}
+
 pub fn render_plain(report: &RunReport) -> String {
     let mut buf = String::new();
     let _ = writeln!(
@@ -245,19 +473,93 @@ pub fn render_plain(report: &RunReport) -> String {
         report.totals.cases_total,
         report.totals.max_points_per_submission
     );
+    let metrics = &report.totals.metrics;
+    let _ = writeln!(
+        &mut buf,
+        "Metrics (min/median/max): lines={}/{}/{}, comments={:.0}%/{:.0}%/{:.0}%, methods={}/{}/{}, nesting={}/{}/{}, complexity={}/{}/{}",
+        metrics.lines.min,
+        metrics.lines.median,
+        metrics.lines.max,
+        metrics.comment_ratio.min * 100.0,
+        metrics.comment_ratio.median * 100.0,
+        metrics.comment_ratio.max * 100.0,
+        metrics.methods.min,
+        metrics.methods.median,
+        metrics.methods.max,
+        metrics.max_nesting.min,
+        metrics.max_nesting.median,
+        metrics.max_nesting.max,
+        metrics.max_complexity.min,
+        metrics.max_complexity.median,
+        metrics.max_complexity.max
+    );
     if !report.security.findings.is_empty() {
         let _ = writeln!(
             &mut buf,
-            "Security: {} flagged file(s).",
-            report.security.flagged_files
+            "Security: {} flagged file(s); {} submission(s) blocked, {} flagged for review, {} penalized.",
+            report.security.flagged_files,
+            report.security.blocked_submissions,
+            report.security.flagged_submissions,
+            report.security.penalized_submissions
         );
         for finding in &report.security.findings {
             let _ = writeln!(&mut buf, "  - {}", finding.file);
+            // class files have no source positions.
+            let compiled = finding.file.ends_with(".class");
             for issue in &finding.issues {
+                let location = if compiled {
+                    "in bytecode".to_owned()
+                } else {
+                    format!(
+                        "at line {}, column {} to line {}, column {}",
+                        issue.line, issue.column, issue.end_line, issue.end_column
+                    )
+                };
+                let _ = writeln!(
+                    &mut buf,
+                    "      {:?} ({:?}) {location}: violation {:?}, rule {:?}, snippet {:?}",
+                    issue.severity, issue.action, issue.violation, issue.rule, issue.snippet
+                );
+                if let Some(message) = &issue.message {
+                    let _ = writeln!(&mut buf, "        {message}");
+                }
+            }
+        }
+    }
+    if let Some(similarity) = &report.similarity {
+        let _ = writeln!(
+            &mut buf,
+            "Similarity: {} suspicious pair(s) among {} submission(s) and {} archived one(s).",
+            similarity.pairs.len(),
+            similarity.submissions,
+            similarity.archived
+        );
+        for pair in &similarity.pairs {
+            let b = match &pair.term {
+                Some(term) => format!("{} of {term}", pair.b),
+                None => pair.b.clone(),
+            };
+            let _ = writeln!(
+                &mut buf,
+                "  - {} and {b}: {:.0}% ({} {:.0}%, {b} {:.0}%), {} shared fingerprint(s)",
+                pair.a,
+                pair.score * 100.0,
+                pair.a,
+                pair.a_share * 100.0,
+                pair.b_share * 100.0,
+                pair.shared_fingerprints
+            );
+            for region in &pair.regions {
                 let _ = writeln!(
                     &mut buf,
-                    "      line {}, column {}: violation {:?}, snippet {:?}",
-                    issue.line, issue.column, issue.violation, issue.snippet
+                    "      {}:{}-{} ~ {}:{}-{} ({} tokens)",
+                    region.a.file,
+                    region.a.start_line,
+                    region.a.end_line,
+                    region.b.file,
+                    region.b.start_line,
+                    region.b.end_line,
+                    region.tokens
                 );
             }
         }
@@ -268,24 +570,61 @@ pub fn render_plain(report: &RunReport) -> String {
             "\nSubmission: {} (path: {}) => {}/{}",
             submission.name, submission.path, submission.points_awarded, submission.max_points
         );
+        if let Some(metrics) = &submission.metrics {
+            let _ = writeln!(
+                &mut buf,
+                "  - metrics: {} line(s) in {} file(s), {:.0}% comments, {} method(s), nesting {}, complexity {} (at most {} per method)",
+                metrics.lines,
+                metrics.files,
+                metrics.comment_ratio * 100.0,
+                metrics.methods,
+                metrics.max_nesting,
+                metrics.complexity,
+                metrics.max_complexity
+            );
+        }
+        if let Some(starter) = &submission.starter {
+            let _ = writeln!(&mut buf, "  - changed vs starter:");
+            for file in &starter.files {
+                match &file.starter {
+                    Some(from) => {
+                        let _ = writeln!(
+                            &mut buf,
+                            "      {} (starter {from}): {} of {} line(s) written",
+                            file.file,
+                            file.written.len(),
+                            file.lines
+                        );
+                        for written in &file.written {
+                            let _ =
+                                writeln!(&mut buf, "        {:>4}: {}", written.line, written.text);
+                        }
+                    }
+                    None => {
+                        let _ = writeln!(
+                            &mut buf,
+                            "      {}: new file, {} line(s)",
+                            file.file, file.lines
+                        );
+                    }
+                }
+            }
+        }
         for case in &submission.cases {
+            let label = case.name.clone().unwrap_or_else(|| case.index.to_string());
             match &case.outcome {
                 CaseOutcome::Correct { output } => {
                     let _ = writeln!(
                         &mut buf,
                         "  - case {} correct (+{} pts)",
-                        case.index, case.points
+                        label, case.points
                     );
                     if !output.is_empty() {
                         let _ = writeln!(&mut buf, "      output: {:?}", output);
                     }
                 }
                 CaseOutcome::Wrong { output, diff } => {
-                    let _ = writeln!(
-                        &mut buf,
-                        "  - case {} wrong (+0/{})",
-                        case.index, case.points
-                    );
+                    let _ = writeln!(&mut buf, "  - case {} wrong (+0/{})", label, case.points);
                     let _ = writeln!(&mut buf, "      expected: {:?}", case.expected);
                     let _ = writeln!(&mut buf, "      got: {:?}", output);
                     let _ = writeln!(
@@ -294,18 +633,162 @@ pub fn render_plain(report: &RunReport) -> String {
                         diff.additions, diff.removals
                     );
                 }
+                CaseOutcome::Failed { exception, message } => {
+                    let _ = writeln!(&mut buf, "  - case {} failed (+0/{})", label, case.points);
+                    let _ = match exception {
+                        Some(exception) => {
+                            writeln!(&mut buf, "      {}: {}", exception, message)
+                        }
+                        None => writeln!(&mut buf, "      {}", message),
+                    };
+                }
                 CaseOutcome::Error { code, reason } => {
                     let _ = writeln!(
                         &mut buf,
                         "  - case {} error code {} ({})",
-                        case.index, code, reason
+                        label, code, reason
                     );
                 }
             }
+            if let Some(exit) = &case.exit {
+                let _ = writeln!(
+                    &mut buf,
+                    "      exit: expected {:?}, got {} => {} (+{}/{})",
+                    exit.expected,
+                    exit.actual,
+                    if exit.passed { "ok" } else { "mismatch" },
+                    if exit.passed { exit.points } else { 0 },
+                    exit.points
+                );
+            }
+            for file in &case.files {
+                let _ = match &file.outcome {
+                    FileOutcome::Match => writeln!(&mut buf, "      file {}: matches", file.name),
+                    FileOutcome::Mismatch {
+                        output,
+                        additions,
+                        removals,
+                    } => writeln!(
+                        &mut buf,
+                        "      file {}: differs (+{} additions, -{} removals), expected {:?}, got {:?}",
+                        file.name, additions, removals, file.expected, output
+                    ),
+                    FileOutcome::Missing => {
+                        writeln!(
+                            &mut buf,
+                            "      file {}: missing (never written)",
+                            file.name
+                        )
+                    }
+                    FileOutcome::Unreadable { reason } => {
+                        writeln!(
+                            &mut buf,
+                            "      file {}: unreadable ({})",
+                            file.name, reason
+                        )
+                    }
+                };
+            }
             if !case.input.is_empty() {
                 let _ = writeln!(&mut buf, "      input: {:?}", case.input);
             }
         }
+        if let Some(found) = &submission.counterexample {
+            let _ = writeln!(
+                &mut buf,
+                "  - stress counterexample (seed {}, shrunk {} -> {} lines)",
+                found.seed, found.original_lines, found.shrunk_lines
+            );
+            let _ = writeln!(&mut buf, "      input: {:?}", found.input);
+            let _ = writeln!(&mut buf, "      expected: {:?}", found.expected);
+            let _ = writeln!(&mut buf, "      got: {:?}", found.output);
+        }
+        if let Some(api) = &submission.api {
+            let _ = writeln!(
+                &mut buf,
+                "  - api: {} problem(s) (+{} pts)",
+                api.findings.len(),
+                api.points_awarded
+            );
+            for finding in &api.findings {
+                let target = match &finding.member {
+                    Some(member) => format!("{}: {}", finding.class, member),
+                    None => finding.class.clone(),
+                };
+                let _ = match &finding.problem {
+                    ApiProblem::MissingClass => {
+                        writeln!(
+                            &mut buf,
+                            "      {target}: class missing (+0/{})",
+                            finding.points
+                        )
+                    }
+                    ApiProblem::MissingMember => {
+                        writeln!(&mut buf, "      {target}: missing (+0/{})", finding.points)
+                    }
+                    ApiProblem::Mismatch { found } => writeln!(
+                        &mut buf,
+                        "      {target}: mismatched (+0/{}), found {:?}",
+                        finding.points, found
+                    ),
+                };
+            }
+        }
+        if let Some(security) = &submission.security {
+            let verdict = if security.blocked {
+                "blocked, not run".to_owned()
+            } else if security.flagged {
+                "flagged for review".to_owned()
+            } else {
+                "ran".to_owned()
+            };
+            let _ = writeln!(
+                &mut buf,
+                "  - security: {} finding(s), {verdict} (-{} pts)",
+                security.issues, security.points_deducted
+            );
+        }
+        if let Some(require) = &submission.require
+            && !require.findings.is_empty()
+        {
+            let _ = writeln!(
+                &mut buf,
+                "  - requirements: {} missing (-{} pts)",
+                require.findings.len(),
+                require.points_deducted
+            );
+            for finding in &require.findings {
+                let _ = writeln!(
+                    &mut buf,
+                    "      {}: found {}, need {} (-{})",
+                    finding.name, finding.found, finding.min, finding.points
+                );
+                if let Some(message) = &finding.message {
+                    let _ = writeln!(&mut buf, "        {message}");
+                }
+            }
+        }
+        if let Some(style) = &submission.style {
+            let _ = writeln!(
+                &mut buf,
+                "  - style: {}/{} pts, {} finding(s)",
+                style.points_awarded,
+                style.max_points,
+                style.findings.len()
+            );
+            for finding in &style.findings {
+                let _ = writeln!(
+                    &mut buf,
+                    "      {}:{}:{} [{}] {} (-{})",
+                    finding.file,
+                    finding.line,
+                    finding.column,
+                    finding.rule.as_ref(),
+                    finding.message,
+                    finding.points
+                );
+            }
+        }
     }
     buf
 }
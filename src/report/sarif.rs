@@ -0,0 +1,232 @@
+//! SARIF 2.1.0 export of the static-analysis findings, for editors and code-review tools.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::{SecurityIssue, SecuritySummary};
+use crate::checker::rules::{Action, Severity};
+use crate::config::TEMPDIR;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Base of every artifact URI: the directory each submission was unpacked into.
+const SUBMISSIONS: &str = "SUBMISSIONS";
+
+#[derive(Serialize)]
+pub struct Log {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    /// Columns count characters, like the rest of the report.
+    column_kind: &'static str,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    rules: Vec<ReportingDescriptor>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportingDescriptor {
+    id: String,
+    short_description: Text,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<RuleProperties>,
+}
+
+#[derive(Serialize)]
+struct RuleProperties {
+    category: String,
+}
+
+#[derive(Serialize)]
+struct Text {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    rule_index: usize,
+    level: &'static str,
+    message: Text,
+    locations: Vec<Location>,
+    properties: ResultProperties,
+}
+
+#[derive(Serialize)]
+struct ResultProperties {
+    submission: String,
+    action: Action,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    region: Region,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+    uri_base_id: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<Text>,
+}
+
+const fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note",
+    }
+}
+
+/// The rule a finding is reported under: the rule or pattern that matched, or its category.
+fn rule_id(issue: &SecurityIssue) -> String {
+    issue
+        .rule
+        .clone()
+        .or_else(|| issue.violation.clone())
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+fn message(issue: &SecurityIssue, id: &str) -> String {
+    match (&issue.message, &issue.violation) {
+        (Some(message), _) => message.clone(),
+        (None, Some(category)) => format!("{category} is not allowed (`{id}`)"),
+        (None, None) => format!("matched `{id}`"),
+    }
+}
+
+/// The path of `file` inside the submission directories, with `/` separators.
+fn relative_uri(file: &str) -> String {
+    let path = Path::new(file);
+    let relative = path.strip_prefix(TEMPDIR.as_path()).unwrap_or(path);
+    relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A SARIF log with one result per finding in `summary`.
+pub fn from_summary(summary: &SecuritySummary) -> Log {
+    // every rule that matched, with its category, in a stable order.
+    let mut rules: BTreeMap<String, Option<String>> = BTreeMap::new();
+    for finding in &summary.findings {
+        for issue in &finding.issues {
+            rules
+                .entry(rule_id(issue))
+                .or_insert_with(|| issue.violation.clone());
+        }
+    }
+    let index: BTreeMap<&str, usize> = rules
+        .keys()
+        .enumerate()
+        .map(|(idx, id)| (id.as_str(), idx))
+        .collect();
+    let mut findings: Vec<_> = summary.findings.iter().collect();
+    findings.sort_by(|a, b| a.file.cmp(&b.file));
+    let mut results = vec![];
+    for finding in findings {
+        let uri = relative_uri(&finding.file);
+        let submission = uri.split('/').next().unwrap_or_default().to_owned();
+        for issue in &finding.issues {
+            let id = rule_id(issue);
+            results.push(SarifResult {
+                rule_index: index.get(id.as_str()).copied().unwrap_or_default(),
+                level: level(issue.severity),
+                message: Text {
+                    text: message(issue, &id),
+                },
+                locations: vec![Location {
+                    physical_location: PhysicalLocation {
+                        artifact_location: ArtifactLocation {
+                            uri: uri.clone(),
+                            uri_base_id: SUBMISSIONS,
+                        },
+                        region: Region {
+                            start_line: issue.line,
+                            start_column: issue.column,
+                            end_line: issue.end_line,
+                            end_column: issue.end_column,
+                            snippet: issue.snippet.clone().map(|text| Text { text }),
+                        },
+                    },
+                }],
+                properties: ResultProperties {
+                    submission: submission.clone(),
+                    action: issue.action,
+                },
+                rule_id: id,
+            });
+        }
+    }
+    Log {
+        schema: SCHEMA,
+        version: "2.1.0",
+        runs: vec![Run {
+            tool: Tool {
+                driver: Driver {
+                    name: env!("CARGO_PKG_NAME"),
+                    version: env!("CARGO_PKG_VERSION"),
+                    information_uri: "https://github.com/shuntia/bestest",
+                    rules: rules
+                        .into_iter()
+                        .map(|(id, category)| ReportingDescriptor {
+                            short_description: Text {
+                                text: match &category {
+                                    Some(category) => format!("{category}: `{id}`"),
+                                    None => format!("`{id}`"),
+                                },
+                            },
+                            properties: category.map(|category| RuleProperties { category }),
+                            id,
+                        })
+                        .collect(),
+                },
+            },
+            column_kind: "unicodeCodePoints",
+            results,
+        }],
+    }
+}
@@ -0,0 +1,506 @@
+//! Similarity between submissions, by winnowing token fingerprints the way MOSS does.
+//! Identifiers, literals, whitespace and comments are normalized away before fingerprinting, so
+//! renaming variables or reformatting a copied solution does not hide it.
+
+use crate::checker::lexer::{Syntax, TokenKind, tokenize};
+use crate::checker::metrics::is_source;
+use crate::config::CONFIG;
+use crate::executable::Language;
+use crate::starter::Starter;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+pub mod archive;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct SimilarityConfig {
+    /// Tokens per fingerprinted k-gram; shorter matches are noise.
+    pub kgram: usize,
+    /// k-grams per winnowing window. Every match of at least `kgram + window - 1` tokens is found.
+    pub window: usize,
+    /// Lowest score of a pair that is reported.
+    pub min_score: f64,
+    /// Directory of the fingerprints of earlier terms, which submissions are compared with too.
+    pub archive: Option<PathBuf>,
+    /// Assignment the archived fingerprints are filed under.
+    pub assignment: Option<String>,
+    /// Term this run's submissions are archived as; they are not archived when unset.
+    pub term: Option<String>,
+}
+
+impl Default for SimilarityConfig {
+    fn default() -> Self {
+        Self {
+            kgram: 10,
+            window: 5,
+            min_score: 0.5,
+            archive: None,
+            assignment: None,
+            term: None,
+        }
+    }
+}
+
+/// Matching regions listed per pair, largest first.
+const MAX_REGIONS: usize = 10;
+
+/// Occurrences of one fingerprint considered when lining up regions, so boilerplate repeated
+/// all over both submissions cannot blow up the comparison.
+const MAX_OCCURRENCES: usize = 8;
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SimilarityReport {
+    /// Submissions compared.
+    pub submissions: usize,
+    /// Submissions of earlier terms they were compared with.
+    pub archived: usize,
+    /// Pairs scoring at least `min_score`, most similar first.
+    pub pairs: Vec<SimilarPair>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SimilarPair {
+    pub a: String,
+    pub b: String,
+    /// Earlier term `b` was archived from; `None` when both are from this run.
+    pub term: Option<String>,
+    /// The larger of `a_share` and `b_share`, so copying a small solution into a large one still
+    /// scores high.
+    pub score: f64,
+    /// Share of the fingerprints of `a` also found in `b`.
+    pub a_share: f64,
+    /// Share of the fingerprints of `b` also found in `a`.
+    pub b_share: f64,
+    pub shared_fingerprints: usize,
+    pub regions: Vec<MatchRegion>,
+}
+
+/// Stretches of the two submissions with the same fingerprints in the same order.
+#[derive(Serialize, Clone, Debug)]
+pub struct MatchRegion {
+    pub a: Span,
+    pub b: Span,
+    /// Length of the region in `a`, in tokens.
+    pub tokens: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Span {
+    /// Path relative to the submission.
+    pub file: String,
+    /// One-based and inclusive.
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// A submission reduced to its fingerprints.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Fingerprints {
+    pub name: String,
+    /// Term of an archived submission.
+    #[serde(skip)]
+    pub term: Option<String>,
+    files: Vec<String>,
+    /// File index and one-based line of every token.
+    tokens: Vec<(usize, usize)>,
+    /// Token positions of the k-grams selected for each hash.
+    prints: HashMap<u64, Vec<usize>>,
+}
+
+/// 64-bit FNV-1a, which unlike the standard hasher is stable across builds, so fingerprints can
+/// be compared with stored ones.
+fn fnv(bytes: impl IntoIterator<Item = u8>, seed: u64) -> u64 {
+    bytes.into_iter().fold(seed, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn keywords(lang: &Language) -> &'static [&'static str] {
+    match lang {
+        Language::Java => &[
+            "abstract",
+            "assert",
+            "boolean",
+            "break",
+            "byte",
+            "case",
+            "catch",
+            "char",
+            "class",
+            "continue",
+            "default",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "extends",
+            "final",
+            "finally",
+            "float",
+            "for",
+            "if",
+            "implements",
+            "import",
+            "instanceof",
+            "int",
+            "interface",
+            "long",
+            "new",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "short",
+            "static",
+            "super",
+            "switch",
+            "synchronized",
+            "this",
+            "throw",
+            "throws",
+            "try",
+            "void",
+            "volatile",
+            "while",
+            "var",
+            "record",
+            "yield",
+            "true",
+            "false",
+            "null",
+        ],
+        Language::Python => &[
+            "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+            "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global",
+            "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise",
+            "return", "try", "while", "with", "yield", "match", "case",
+        ],
+        Language::Rust => &[
+            "as", "break", "const", "continue", "crate", "else", "enum", "fn", "for", "if", "impl",
+            "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self",
+            "Self", "static", "struct", "trait", "type", "unsafe", "use", "where", "while", "true",
+            "false",
+        ],
+        Language::C | Language::Cpp | Language::Unknown(_) | Language::Guess => &[
+            "auto",
+            "bool",
+            "break",
+            "case",
+            "catch",
+            "char",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "extern",
+            "float",
+            "for",
+            "if",
+            "inline",
+            "int",
+            "long",
+            "namespace",
+            "new",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "short",
+            "signed",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "template",
+            "this",
+            "throw",
+            "try",
+            "typedef",
+            "union",
+            "unsigned",
+            "using",
+            "virtual",
+            "void",
+            "volatile",
+            "while",
+            "true",
+            "false",
+            "nullptr",
+        ],
+    }
+}
+
+/// Hash of a token with its identity removed: keywords and punctuation stay, every other
+/// identifier and every literal of a kind look the same.
+fn token_hash(kind: TokenKind, text: &str, lang: &Language) -> u64 {
+    let text = match kind {
+        TokenKind::Ident if !keywords(lang).contains(&text) => "id",
+        TokenKind::Number => "0",
+        TokenKind::Str => "\"\"",
+        TokenKind::Char => "''",
+        TokenKind::Ident | TokenKind::Punct => text,
+    };
+    fnv(text.bytes(), FNV_OFFSET)
+}
+
+/// Winnowing: the smallest hash of every window of `window` consecutive k-gram hashes, the
+/// rightmost one on ties, each selected position once.
+fn winnow(hashes: &[u64], window: usize) -> Vec<(u64, usize)> {
+    let window = window.max(1);
+    let mut ret: Vec<(u64, usize)> = vec![];
+    if hashes.is_empty() {
+        return ret;
+    }
+    for start in 0..=hashes.len().saturating_sub(window) {
+        let end = (start + window).min(hashes.len());
+        let mut min = start;
+        for i in start..end {
+            if hashes[i] <= hashes[min] {
+                min = i;
+            }
+        }
+        if ret.last().is_none_or(|&(_, pos)| pos != min) {
+            ret.push((hashes[min], min));
+        }
+    }
+    ret
+}
+
+impl Fingerprints {
+    /// Fingerprints of every source file under `dir`.
+    pub fn of_dir(dir: &Path, config: &SimilarityConfig) -> Self {
+        let mut sources = vec![];
+        for entry in WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(Result::ok)
+        {
+            let path = entry.path();
+            let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+                continue;
+            };
+            // dependencies are the same in every submission, so they would make them all alike.
+            if !path.is_file() || !is_source(ext) || CONFIG.is_dependency(dir, path) {
+                continue;
+            }
+            let relative = path.strip_prefix(dir).unwrap_or(path);
+            match read_to_string(path) {
+                Ok(src) => sources.push((relative.display().to_string(), Language::from(ext), src)),
+                Err(e) => error!("Failed to read {}: {e}", path.display()),
+            }
+        }
+        let name = dir.file_name().map_or_else(
+            || dir.display().to_string(),
+            |name| name.to_string_lossy().into(),
+        );
+        Self::of_sources(name, &sources, config)
+    }
+
+    /// Fingerprints of `(path, language, source)` triples making up one submission.
+    pub fn of_sources(
+        name: String,
+        sources: &[(String, Language, String)],
+        config: &SimilarityConfig,
+    ) -> Self {
+        let kgram = config.kgram.max(1);
+        let mut ret = Self {
+            name,
+            term: None,
+            files: vec![],
+            tokens: vec![],
+            prints: HashMap::new(),
+        };
+        for (file, (path, lang, src)) in sources.iter().enumerate() {
+            let tokens = tokenize(src, &Syntax::of(lang));
+            let hashes: Vec<u64> = tokens
+                .iter()
+                .map(|token| token_hash(token.kind, &token.text, lang))
+                .collect();
+            // k-grams never span two files.
+            let grams: Vec<u64> = hashes
+                .windows(kgram)
+                .map(|gram| fnv(gram.iter().flat_map(|hash| hash.to_le_bytes()), FNV_OFFSET))
+                .collect();
+            let offset = ret.tokens.len();
+            for (hash, pos) in winnow(&grams, config.window) {
+                ret.prints.entry(hash).or_default().push(offset + pos);
+            }
+            ret.files.push(path.clone());
+            ret.tokens
+                .extend(tokens.iter().map(|token| (file, token.start.0 + 1)));
+        }
+        ret
+    }
+
+    /// Drops every fingerprint `other` also has, such as the starter code's.
+    pub fn exclude(&mut self, other: &Self) {
+        self.prints
+            .retain(|hash, _| !other.prints.contains_key(hash));
+    }
+
+    fn span(&self, from: usize, to: usize) -> Span {
+        let (file, start_line) = self.tokens[from];
+        Span {
+            file: self.files[file].clone(),
+            start_line,
+            end_line: self.tokens[to.saturating_sub(1).max(from)].1,
+        }
+    }
+}
+
+/// Compares two fingerprinted submissions.
+pub fn compare(a: &Fingerprints, b: &Fingerprints, config: &SimilarityConfig) -> SimilarPair {
+    let shared: Vec<u64> = a
+        .prints
+        .keys()
+        .filter(|hash| b.prints.contains_key(hash))
+        .copied()
+        .collect();
+    let share = |of: &Fingerprints| {
+        if of.prints.is_empty() {
+            0.0
+        } else {
+            shared.len() as f64 / of.prints.len() as f64
+        }
+    };
+    let (a_share, b_share) = (share(a), share(b));
+    let score = a_share.max(b_share);
+    SimilarPair {
+        a: a.name.clone(),
+        b: b.name.clone(),
+        term: b.term.clone(),
+        score,
+        a_share,
+        b_share,
+        shared_fingerprints: shared.len(),
+        // only reported pairs need them.
+        regions: if score >= config.min_score {
+            regions(a, b, &shared, config)
+        } else {
+            vec![]
+        },
+    }
+}
+
+/// Lines up shared fingerprints into regions: a match continues while both sides keep advancing
+/// within the same files and no more than a window apart.
+fn regions(
+    a: &Fingerprints,
+    b: &Fingerprints,
+    shared: &[u64],
+    config: &SimilarityConfig,
+) -> Vec<MatchRegion> {
+    let kgram = config.kgram.max(1);
+    let gap = kgram + config.window;
+    let mut matches: Vec<(usize, usize)> = shared
+        .iter()
+        .flat_map(|hash| {
+            let bs = &b.prints[hash];
+            a.prints[hash]
+                .iter()
+                .take(MAX_OCCURRENCES)
+                .flat_map(move |&p| bs.iter().take(MAX_OCCURRENCES).map(move |&q| (p, q)))
+        })
+        .collect();
+    matches.sort_unstable();
+    // (start in a, end in a, start in b, end in b), ends exclusive.
+    let mut found: Vec<(usize, usize, usize, usize)> = vec![];
+    for (p, q) in matches {
+        let file_a = a.tokens[p].0;
+        let file_b = b.tokens[q].0;
+        let extends = found.iter_mut().rev().find(|region| {
+            let (a_start, a_end, b_start, b_end) = **region;
+            p <= a_end + gap
+                && q >= b_start
+                && q <= b_end + gap
+                && a.tokens[a_start].0 == file_a
+                && b.tokens[b_start].0 == file_b
+        });
+        match extends {
+            Some(region) => {
+                region.1 = region.1.max(p + kgram);
+                region.3 = region.3.max(q + kgram);
+            }
+            None => found.push((p, p + kgram, q, q + kgram)),
+        }
+    }
+    found.sort_by_key(|&(a_start, a_end, ..)| (std::cmp::Reverse(a_end - a_start), a_start));
+    found
+        .into_iter()
+        .take(MAX_REGIONS)
+        .map(|(a_start, a_end, b_start, b_end)| MatchRegion {
+            a: a.span(a_start, a_end.min(a.tokens.len())),
+            b: b.span(b_start, b_end.min(b.tokens.len())),
+            tokens: a_end - a_start,
+        })
+        .collect()
+}
+
+/// Compares every pair of submissions in `dirs`, and each of them with the archived ones,
+/// leaving out code that matches `starter`. The submissions are archived afterwards when a term
+/// is configured.
+pub fn compare_dirs(
+    dirs: &[PathBuf],
+    config: &SimilarityConfig,
+    starter: &Starter,
+) -> SimilarityReport {
+    let mut dirs = dirs.to_vec();
+    dirs.sort();
+    let starter = Fingerprints::of_sources("starter".to_owned(), &starter.sources(), config);
+    let fingerprinted: Vec<Fingerprints> = dirs
+        .iter()
+        .map(|dir| Fingerprints::of_dir(dir, config))
+        .collect();
+    let without_starter = |mut prints: Fingerprints| {
+        prints.exclude(&starter);
+        prints
+    };
+    let prints: Vec<Fingerprints> = fingerprinted.iter().cloned().map(without_starter).collect();
+    let archived: Vec<Fingerprints> = archive::load(config)
+        .into_iter()
+        .map(without_starter)
+        .collect();
+    let mut pairs = vec![];
+    for (i, a) in prints.iter().enumerate() {
+        for b in prints[i + 1..].iter().chain(&archived) {
+            let pair = compare(a, b, config);
+            debug!("Similarity of {} and {}: {:.2}", a.name, b.name, pair.score);
+            if pair.score >= config.min_score && pair.shared_fingerprints > 0 {
+                pairs.push(pair);
+            }
+        }
+    }
+    pairs.sort_by(|x, y| y.score.total_cmp(&x.score));
+    info!(
+        "Similarity check complete: {} suspicious pair(s) among {} submission(s) and {} archived one(s).",
+        pairs.len(),
+        prints.len(),
+        archived.len()
+    );
+    if let Some(term) = &config.term {
+        // the starter code is left out when comparing, so archive everything.
+        match archive::store(config, term, fingerprinted) {
+            Ok(()) => info!("Archived {} submission(s) as {term}.", prints.len()),
+            Err(e) => error!("Failed to archive the submissions: {e:#}"),
+        }
+    }
+    SimilarityReport {
+        submissions: prints.len(),
+        archived: archived.len(),
+        pairs,
+    }
+}
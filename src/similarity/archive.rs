@@ -0,0 +1,138 @@
+//! Fingerprints of earlier terms, kept on disk so new submissions can be compared with the work
+//! of students who took the course before. Each assignment is a directory in the archive with
+//! one JSON file per term.
+
+use super::{Fingerprints, SimilarityConfig};
+use anyhow::{Context, Result, bail};
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, create_dir_all, read_dir};
+use std::path::{Path, PathBuf};
+
+/// The submissions of one term.
+#[derive(Serialize, Deserialize)]
+pub struct Term {
+    pub term: String,
+    /// Settings the fingerprints were taken with; other settings give incomparable ones.
+    pub kgram: usize,
+    pub window: usize,
+    pub submissions: Vec<Fingerprints>,
+}
+
+/// A name usable as a file name: anything but letters, digits, `-`, `_` and `.` becomes `_`.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || ['-', '_', '.'].contains(&c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Directory of the configured assignment, when the archive is configured.
+fn assignment_dir(config: &SimilarityConfig) -> Option<PathBuf> {
+    let archive = config.archive.as_ref()?;
+    match &config.assignment {
+        Some(assignment) => Some(archive.join(file_name(assignment))),
+        None => {
+            error!("`similarity.archive` is set without `similarity.assignment`; ignoring it.");
+            None
+        }
+    }
+}
+
+fn term_path(dir: &Path, term: &str) -> PathBuf {
+    dir.join(format!("{}.json", file_name(term)))
+}
+
+/// Every archived submission of the configured assignment, except those of the term this run
+/// is stored under.
+pub fn load(config: &SimilarityConfig) -> Vec<Fingerprints> {
+    let Some(dir) = assignment_dir(config) else {
+        return vec![];
+    };
+    let entries = match read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read the archive at {}: {e}", dir.display());
+            return vec![];
+        }
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    let mut ret = vec![];
+    for path in paths {
+        let term: Term = match File::open(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| Ok(serde_json::from_reader(file)?))
+        {
+            Ok(term) => term,
+            Err(e) => {
+                error!("Failed to read archived term {}: {e}", path.display());
+                continue;
+            }
+        };
+        if config.term.as_ref() == Some(&term.term) {
+            continue;
+        }
+        if (term.kgram, term.window) != (config.kgram, config.window) {
+            warn!(
+                "Archived term {} was fingerprinted with kgram = {} and window = {}; skipping it.",
+                term.term, term.kgram, term.window
+            );
+            continue;
+        }
+        debug!(
+            "Loaded {} archived submission(s) of {}.",
+            term.submissions.len(),
+            term.term
+        );
+        ret.extend(term.submissions.into_iter().map(|mut submission| {
+            submission.term = Some(term.term.clone());
+            submission
+        }));
+    }
+    ret
+}
+
+/// Stores `submissions` as the configured assignment's `term`, replacing what was stored for it.
+pub fn store(config: &SimilarityConfig, term: &str, submissions: Vec<Fingerprints>) -> Result<()> {
+    let Some(dir) = assignment_dir(config) else {
+        bail!("the archive needs `similarity.archive` and `similarity.assignment`");
+    };
+    create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let path = term_path(&dir, term);
+    let file =
+        File::create(&path).with_context(|| format!("failed to create {}", path.display()))?;
+    serde_json::to_writer(
+        file,
+        &Term {
+            term: term.to_owned(),
+            kgram: config.kgram,
+            window: config.window,
+            submissions,
+        },
+    )
+    .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Fingerprints every submission directory in `dirs` and stores them as `term`. Directories
+/// without source code are left out.
+pub fn import(dirs: &[PathBuf], config: &SimilarityConfig, term: &str) -> Result<usize> {
+    let mut submissions: Vec<Fingerprints> = dirs
+        .iter()
+        .map(|dir| Fingerprints::of_dir(dir, config))
+        .filter(|submission| !submission.prints.is_empty())
+        .collect();
+    submissions.sort_by(|a, b| a.name.cmp(&b.name));
+    let count = submissions.len();
+    store(config, term, submissions)?;
+    Ok(count)
+}
@@ -0,0 +1,165 @@
+//! Starter code handed out with an assignment. Every submission shares it, so it is left out of
+//! the similarity check, and each submission's report can show only the lines written on top of it.
+
+use crate::checker::metrics::is_source;
+use crate::config::CONFIG;
+use crate::executable::Language;
+use crate::test::compare;
+use log::{error, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Source files of the starter code, by file name.
+#[derive(Default)]
+pub struct Starter {
+    files: HashMap<String, (PathBuf, String)>,
+}
+
+/// What a submission changed compared to the starter code.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct StarterChanges {
+    pub files: Vec<FileChanges>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FileChanges {
+    /// Path relative to the submission.
+    pub file: String,
+    /// The starter file it was compared with; `None` for files the student added.
+    pub starter: Option<String>,
+    /// Non-blank lines in the submission's file.
+    pub lines: usize,
+    /// Lines that are not in the starter file, ignoring indentation. Empty for added files,
+    /// which are written entirely by the student.
+    pub written: Vec<WrittenLine>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct WrittenLine {
+    /// One-based.
+    pub line: usize,
+    pub text: String,
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+/// Source files under `root`, which may be a single file, with paths relative to `root` (the
+/// file name for a single file).
+fn sources(root: &Path) -> Vec<(String, PathBuf)> {
+    WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(walkdir::DirEntry::into_path)
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(is_source)
+        })
+        .filter_map(|path| {
+            let relative = if path == root {
+                file_name(&path)?
+            } else {
+                path.strip_prefix(root).ok()?.display().to_string()
+            };
+            Some((relative, path))
+        })
+        .collect()
+}
+
+/// Lines with indentation and trailing whitespace removed, so reindenting is not a change.
+fn normalized(src: &str) -> String {
+    src.lines()
+        .map(|line| format!("{}\n", line.trim()))
+        .collect()
+}
+
+impl Starter {
+    /// Reads the starter files and directories in `paths`.
+    pub fn load(paths: &[PathBuf]) -> Self {
+        let mut ret = Self::default();
+        for root in paths {
+            if !root.exists() {
+                warn!("Starter code {} does not exist; skipping.", root.display());
+                continue;
+            }
+            for (_, path) in sources(root) {
+                let Some(name) = file_name(&path) else {
+                    continue;
+                };
+                match read_to_string(&path) {
+                    Ok(src) => {
+                        if ret.files.insert(name.clone(), (path, src)).is_some() {
+                            warn!("Several starter files are named {name}; using the last one.");
+                        }
+                    }
+                    Err(e) => error!("Failed to read {}: {e}", path.display()),
+                }
+            }
+        }
+        ret
+    }
+
+    /// The starter files as `(path, language, source)`, the way they are fingerprinted.
+    pub fn sources(&self) -> Vec<(String, Language, String)> {
+        let mut ret: Vec<_> = self
+            .files
+            .iter()
+            .map(|(name, (path, src))| {
+                let ext = path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or_default();
+                (name.clone(), Language::from(ext), src.clone())
+            })
+            .collect();
+        ret.sort_by(|a, b| a.0.cmp(&b.0));
+        ret
+    }
+
+    /// Compares every source file of the submission in `dir` with the starter file of the same
+    /// name. Copies of the `dependencies` are not the student's and are left out.
+    pub fn changes(&self, dir: &Path) -> StarterChanges {
+        let mut files = vec![];
+        for (relative, path) in sources(dir) {
+            if CONFIG.is_dependency(dir, &path) {
+                continue;
+            }
+            let src = match read_to_string(&path) {
+                Ok(src) => src,
+                Err(e) => {
+                    error!("Failed to read {}: {e}", path.display());
+                    continue;
+                }
+            };
+            let lines = src.lines().filter(|line| !line.trim().is_empty()).count();
+            let starter = file_name(&path).and_then(|name| self.files.get(&name));
+            let written = starter.map_or_else(Vec::new, |(_, starter_src)| {
+                let diff = compare(&normalized(starter_src), &normalized(&src));
+                src.lines()
+                    .enumerate()
+                    .filter(|&(idx, line)| !line.trim().is_empty() && diff.is_added(idx as u32))
+                    .map(|(idx, line)| WrittenLine {
+                        line: idx + 1,
+                        text: line.trim_end().to_owned(),
+                    })
+                    .collect()
+            });
+            files.push(FileChanges {
+                file: relative,
+                starter: starter.and_then(|(path, _)| file_name(path)),
+                lines,
+                written,
+            });
+        }
+        StarterChanges { files }
+    }
+}
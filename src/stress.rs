@@ -0,0 +1,302 @@
+use crate::config::{CONFIG, MULTIPROG, TEMPDIR};
+use crate::lang::runner::{self, Runner};
+use crate::test::{TestResult, arguments, environment, execute};
+use crate::unpacker::copy_dir;
+use anyhow::{Context, Result, bail};
+use console::style;
+use core::time::Duration;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs::{copy, create_dir_all};
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+
+/// Differential stress testing against a reference solution.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct StressConfig {
+    /// Reference solution; either a single source file or a directory.
+    pub reference: PathBuf,
+    /// Generator command. The seed of each round is appended as the last argument.
+    pub generator: Vec<String>,
+    /// Number of random inputs to try per submission.
+    #[serde(default = "default_iterations")]
+    pub iterations: u64,
+    /// Seed of the first round; round `n` uses `seed + n`.
+    #[serde(default)]
+    pub seed: u64,
+    /// Upper bound on program pairs executed while shrinking a counterexample.
+    #[serde(default = "default_shrink_attempts")]
+    pub shrink_attempts: u64,
+    /// Only stress submissions that already failed at least one case.
+    #[serde(default = "default_only_failing")]
+    pub only_failing: bool,
+}
+
+const fn default_iterations() -> u64 {
+    100
+}
+
+const fn default_shrink_attempts() -> u64 {
+    200
+}
+
+const fn default_only_failing() -> bool {
+    true
+}
+
+/// Smallest input found on which a submission disagrees with the reference.
+#[derive(Serialize, Clone, Debug)]
+pub struct Counterexample {
+    pub seed: u64,
+    pub input: String,
+    pub expected: String,
+    pub output: String,
+    pub original_lines: usize,
+    pub shrunk_lines: usize,
+}
+
+/// Outcome of running both programs on a single input.
+enum Verdict {
+    Agree,
+    Differ {
+        expected: String,
+        output: String,
+    },
+    /// The reference itself failed, so the input is not usable.
+    Invalid,
+}
+
+pub async fn stress_dirs(
+    dirs: Vec<PathBuf>,
+    cfg: &'static StressConfig,
+) -> Result<HashMap<PathBuf, Counterexample>> {
+    if cfg.generator.is_empty() {
+        bail!("stress.generator must name a command");
+    }
+    let reference = prepare_reference(&cfg.reference).await?;
+    let semaphore = Arc::new(Semaphore::new(
+        usize::try_from(CONFIG.threads.max(1)).context("thread count exceeds usize range")?,
+    ));
+    let mp = MULTIPROG.lock().await;
+    let op = mp.add(ProgressBar::new(dirs.len() as u64));
+    op.set_style(
+        ProgressStyle::default_bar()
+            .template(
+                "[{elapsed_precise}] stress testing... [{wide_bar:.bold.cyan/blue}] ({pos}/{len})",
+            )
+            .unwrap_or_else(|err| {
+                warn!("Failed to configure stress progress style: {err}");
+                ProgressStyle::default_bar()
+            })
+            .progress_chars("\u{2588}\u{e0b0}\u{2500}"),
+    );
+    op.enable_steady_tick(Duration::from_millis(100));
+    drop(mp);
+    let mut handles = vec![];
+    for dir in dirs {
+        let semaphore = Arc::clone(&semaphore);
+        let reference = reference.clone();
+        let op = op.clone();
+        handles.push(tokio::task::spawn(async move {
+            let permit = semaphore.acquire().await;
+            let found = match permit {
+                Ok(_permit) => stress_dir(&dir, &reference, cfg).await,
+                Err(e) => {
+                    error!("Failed to acquire semaphore: {e}");
+                    None
+                }
+            };
+            op.inc(1);
+            (dir, found)
+        }));
+    }
+    let mut ret = HashMap::new();
+    for handle in handles {
+        let (dir, found) = handle.await.context("Stress task panicked")?;
+        if let Some(found) = found {
+            ret.insert(dir, found);
+        }
+    }
+    op.finish_and_clear();
+    info!(
+        "Stress testing complete: {} counterexample(s) found.",
+        ret.len()
+    );
+    Ok(ret)
+}
+
+/// Copies the reference solution into its own venv and compiles it once.
+async fn prepare_reference(reference: &Path) -> Result<PathBuf> {
+    let venv = TEMPDIR.join(".reference");
+    create_dir_all(&venv)
+        .await
+        .context("failed to create reference directory")?;
+    if reference.is_dir() {
+        copy_dir(reference, &venv)
+            .await
+            .with_context(|| format!("failed to copy reference {}", reference.display()))?;
+    } else {
+        let file_name = reference
+            .file_name()
+            .context("reference path does not have a file name")?;
+        copy(reference, venv.join(file_name))
+            .await
+            .with_context(|| format!("failed to copy reference {}", reference.display()))?;
+    }
+    let mut proc = runner::from_dir(venv.clone(), None)
+        .await
+        .context("failed to initialize runner for reference solution")?;
+    if let Err(e) = proc.prepare().await {
+        bail!("reference solution failed to compile: {e:?}");
+    }
+    Ok(venv)
+}
+
+async fn stress_dir(dir: &Path, reference: &Path, cfg: &StressConfig) -> Option<Counterexample> {
+    let mut student = runner::from_dir(dir.to_path_buf(), None).await?;
+    let mut oracle = runner::from_dir(reference.to_path_buf(), None).await?;
+    if student.prepare().await.is_err() {
+        debug!("{} does not compile; skipping stress test.", dir.display());
+        return None;
+    }
+    for proc in [&mut student, &mut oracle] {
+        proc.args(arguments(None)).await;
+        proc.envs(environment(None)).await;
+    }
+    for round in 0..cfg.iterations {
+        let seed = cfg.seed.wrapping_add(round);
+        let input = match generate(&cfg.generator, seed).await {
+            Ok(input) => input,
+            Err(e) => {
+                error!("Generator failed for seed {seed}: {e:#}");
+                return None;
+            }
+        };
+        let Verdict::Differ { expected, output } =
+            compare(dir, &mut student, &mut oracle, &input).await
+        else {
+            continue;
+        };
+        let original_lines = input.lines().count();
+        let (input, expected, output) =
+            shrink(dir, &mut student, &mut oracle, cfg, input, expected, output).await;
+        warn!(
+            "{} {} disagrees with the reference on seed {seed} ({} line(s) after shrinking).",
+            style("[ST]").red().bold(),
+            dir.display(),
+            input.lines().count()
+        );
+        return Some(Counterexample {
+            seed,
+            shrunk_lines: input.lines().count(),
+            input,
+            expected,
+            output,
+            original_lines,
+        });
+    }
+    debug!(
+        "{} agreed with the reference on {} random input(s).",
+        dir.display(),
+        cfg.iterations
+    );
+    None
+}
+
+async fn generate(generator: &[String], seed: u64) -> Result<String> {
+    let (program, args) = generator
+        .split_first()
+        .context("generator command is empty")?;
+    let child = Command::new(program)
+        .args(args)
+        .arg(seed.to_string())
+        .kill_on_drop(true)
+        .output();
+    let out = tokio::time::timeout(Duration::from_millis(CONFIG.timeout), child)
+        .await
+        .context("generator timed out")?
+        .with_context(|| format!("failed to spawn generator `{program}`"))?;
+    if !out.status.success() {
+        bail!(
+            "generator exited with {}: {}",
+            out.status,
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+async fn compare(
+    dir: &Path,
+    student: &mut Box<dyn Runner>,
+    oracle: &mut Box<dyn Runner>,
+    input: &str,
+) -> Verdict {
+    let expected = match execute(dir, oracle, input.to_owned()).await {
+        Ok(out) => out.output,
+        Err(e) => {
+            debug!("Reference rejected generated input: {e:?}");
+            return Verdict::Invalid;
+        }
+    };
+    let output = match execute(dir, student, input.to_owned()).await {
+        Ok(out) => out.output,
+        Err(TestResult::Error { code, reason }) => format!("<error code {code}: {reason}>"),
+        Err(e) => format!("<{e:?}>"),
+    };
+    if output == expected {
+        Verdict::Agree
+    } else {
+        Verdict::Differ { expected, output }
+    }
+}
+
+/// Line-based delta debugging: repeatedly drops chunks of lines while the programs still disagree.
+async fn shrink(
+    dir: &Path,
+    student: &mut Box<dyn Runner>,
+    oracle: &mut Box<dyn Runner>,
+    cfg: &StressConfig,
+    input: String,
+    mut expected: String,
+    mut output: String,
+) -> (String, String, String) {
+    let mut lines: Vec<String> = input.split_inclusive('\n').map(str::to_owned).collect();
+    let mut attempts = 0u64;
+    let mut granularity = 2usize;
+    while lines.len() > 1 && attempts < cfg.shrink_attempts {
+        let chunk = lines.len().div_ceil(granularity);
+        let mut reduced = false;
+        let mut start = 0;
+        while start < lines.len() && attempts < cfg.shrink_attempts {
+            let end = (start + chunk).min(lines.len());
+            let candidate: String = lines[..start].concat() + &lines[end..].concat();
+            attempts += 1;
+            if let Verdict::Differ {
+                expected: e,
+                output: o,
+            } = compare(dir, student, oracle, &candidate).await
+            {
+                lines.drain(start..end);
+                expected = e;
+                output = o;
+                reduced = true;
+                break;
+            }
+            start = end;
+        }
+        if reduced {
+            granularity = granularity.saturating_sub(1).max(2);
+        } else if granularity >= lines.len() {
+            break;
+        } else {
+            granularity = (granularity * 2).min(lines.len());
+        }
+    }
+    (lines.concat(), expected, output)
+}
@@ -1,23 +1,198 @@
+use crate::checker::{IllegalExpr, bytecode, rules::Action};
 use crate::config;
-use crate::config::{CONFIG, MULTIPROG};
-use crate::executable::Language;
+use crate::config::{CONFIG, MULTIPROG, SIMPLEOPTS, TEMPDIR};
 use crate::lang::runner::{self, RunError, Runner};
+use crate::unit;
+use crate::unpacker::copy_dir;
 use anyhow::{Context, Result};
 use console::style;
 use core::time::Duration;
 use imara_diff::{Algorithm, Diff, InternedInput};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use itertools::Itertools as _;
 use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
 use std::sync::Arc;
+use tokio::fs::{copy, create_dir_all, read_to_string, remove_dir_all, write};
 use tokio::sync::{Mutex, MutexGuard, Semaphore};
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 #[non_exhaustive]
 pub struct TestCase {
+    /// Label shown in reports; unit tests use `Class.method`.
+    pub name: Option<String>,
     pub input: String,
     pub expected: String,
     pub points: u64,
+    /// Program arguments appended after the global `args`.
+    pub args: Vec<String>,
+    /// Environment variables layered over the global `env`.
+    pub env: HashMap<String, String>,
+    /// How the program is expected to terminate. Unchecked when absent.
+    pub exit: Option<ExitExpectation>,
+    /// Points awarded when the termination matches `exit`.
+    pub exit_points: u64,
+    /// Files written into the working directory before the run, by relative name.
+    pub files: BTreeMap<String, String>,
+    /// Files the program must leave behind, compared like stdout.
+    pub output_files: BTreeMap<String, String>,
+    /// Files or directories copied into this case's working directory only.
+    pub fixtures: Vec<PathBuf>,
+}
+
+impl Default for TestCase {
+    fn default() -> Self {
+        Self {
+            name: None,
+            input: String::new(),
+            expected: String::new(),
+            points: 1,
+            args: vec![],
+            env: HashMap::new(),
+            exit: None,
+            exit_points: 1,
+            files: BTreeMap::new(),
+            output_files: BTreeMap::new(),
+            fixtures: vec![],
+        }
+    }
+}
+
+impl TestCase {
+    /// Points available for this case, including the exit condition.
+    #[must_use]
+    pub const fn max_points(&self) -> u64 {
+        if self.exit.is_some() {
+            self.points + self.exit_points
+        } else {
+            self.points
+        }
+    }
+}
+
+/// Expected termination of a program.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ExitExpectation {
+    /// Exits normally with exactly this code.
+    Code(i32),
+    /// Exits normally with any code other than zero.
+    NonZero,
+    /// Is terminated by this signal, e.g. `"SIGSEGV"`.
+    Signal(String),
+}
+
+impl ExitExpectation {
+    #[must_use]
+    pub fn matches(&self, status: &ExitStatus) -> bool {
+        match self {
+            Self::Code(code) => status.code() == Some(*code),
+            Self::NonZero => status.code().is_some_and(|code| code != 0),
+            Self::Signal(name) => {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::process::ExitStatusExt as _;
+                    let Some(raw) = status.signal() else {
+                        return false;
+                    };
+                    let name = name.trim().to_ascii_uppercase();
+                    if let Ok(number) = name.parse::<i32>() {
+                        return number == raw;
+                    }
+                    let name = if name.starts_with("SIG") {
+                        name
+                    } else {
+                        format!("SIG{name}")
+                    };
+                    nix::sys::signal::Signal::try_from(raw).is_ok_and(|sig| sig.as_str() == name)
+                }
+                #[cfg(not(unix))]
+                {
+                    let _ = name;
+                    false
+                }
+            }
+        }
+    }
+}
+
+/// Result of checking a case's exit condition.
+#[derive(Serialize, Clone, Debug)]
+pub struct ExitVerdict {
+    pub expected: ExitExpectation,
+    pub actual: String,
+    pub passed: bool,
+    pub points: u64,
+}
+
+impl ExitVerdict {
+    fn new(expected: &ExitExpectation, status: &ExitStatus, points: u64) -> Self {
+        Self {
+            expected: expected.clone(),
+            actual: describe_status(status),
+            passed: expected.matches(status),
+            points,
+        }
+    }
+}
+
+fn describe_status(status: &ExitStatus) -> String {
+    if let Some(code) = status.code() {
+        return format!("code {code}");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt as _;
+        if let Some(raw) = status.signal() {
+            return nix::sys::signal::Signal::try_from(raw).map_or_else(
+                |_| format!("signal {raw}"),
+                |sig| format!("signal {}", sig.as_str()),
+            );
+        }
+    }
+    status.to_string()
+}
+
+/// Result of comparing one output file after a run.
+#[derive(Serialize, Clone, Debug)]
+pub struct FileVerdict {
+    pub name: String,
+    pub expected: String,
+    pub outcome: FileOutcome,
+}
+
+impl FileVerdict {
+    #[must_use]
+    pub const fn passed(&self) -> bool {
+        matches!(self.outcome, FileOutcome::Match)
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FileOutcome {
+    Match,
+    Mismatch {
+        output: String,
+        additions: usize,
+        removals: usize,
+    },
+    /// The program did not create the file.
+    Missing,
+    Unreadable {
+        reason: String,
+    },
+}
+
+/// Output of a single program run.
+#[derive(Debug)]
+pub struct Execution {
+    pub output: String,
+    pub status: ExitStatus,
 }
 impl core::fmt::Display for TestCase {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
@@ -36,6 +211,8 @@ pub enum TestResult {
     Correct {
         case: &'static TestCase,
         output: String,
+        exit: Option<ExitVerdict>,
+        files: Vec<FileVerdict>,
     },
     Error {
         reason: String,
@@ -45,14 +222,22 @@ pub enum TestResult {
         case: &'static TestCase,
         output: String,
         diff: Diff,
+        exit: Option<ExitVerdict>,
+        files: Vec<FileVerdict>,
+    },
+    /// A unit test that failed an assertion or threw.
+    Failed {
+        case: &'static TestCase,
+        exception: Option<String>,
+        message: String,
     },
 }
 
 impl TestResult {
-    pub const fn is_correct(&self) -> bool {
+    pub fn is_correct(&self) -> bool {
         match self {
-            Self::Correct { .. } => true,
-            Self::Wrong { .. } | Self::Error { .. } => false,
+            Self::Correct { exit, .. } => exit.as_ref().is_none_or(|verdict| verdict.passed),
+            Self::Wrong { .. } | Self::Error { .. } | Self::Failed { .. } => false,
         }
     }
     #[must_use]
@@ -65,9 +250,14 @@ impl TestResult {
     }
 }
 
+/// Compiles and tests every submission. Also returns the findings of the bytecode check, keyed
+/// by class file, when `bytecode` is enabled.
 pub async fn test_dirs<T: IntoIterator<Item = PathBuf>>(
     p: T,
-) -> Result<Vec<(PathBuf, Vec<TestResult>)>> {
+) -> Result<(
+    Vec<(PathBuf, Vec<TestResult>)>,
+    HashMap<PathBuf, Vec<IllegalExpr>>,
+)> {
     let cfg = config::get_config()?;
     let max_threads = cfg.threads.max(1);
     let semaphore = Arc::new(Semaphore::new(
@@ -102,12 +292,21 @@ pub async fn test_dirs<T: IntoIterator<Item = PathBuf>>(
     drop(arcmp);
     debug!("Processing: {v:#?}");
     let mut ret = vec![];
+    let mut findings = HashMap::new();
     for handle in handles {
         let out = handle.await.context("Test task panicked")?;
+        for (path, issues) in out
+            .2
+            .into_iter()
+            .chunk_by(|issue| issue.path.clone())
+            .into_iter()
+        {
+            findings.insert(path, issues.collect());
+        }
         match out.1 {
             Err(RunError::RE(code, reason)) => {
                 let code_value = code.unwrap_or(-1);
-                let errors = (0..CONFIG.testcases.len())
+                let errors = (0..CONFIG.cases().count())
                     .map(|_| TestResult::Error {
                         reason: reason.clone(),
                         code: code_value,
@@ -117,7 +316,7 @@ pub async fn test_dirs<T: IntoIterator<Item = PathBuf>>(
             }
             Err(RunError::CE(code, reason)) => {
                 let code_value = code.unwrap_or(-1);
-                let errors = (0..CONFIG.testcases.len())
+                let errors = (0..CONFIG.cases().count())
                     .map(|_| TestResult::Error {
                         reason: reason.clone(),
                         code: code_value,
@@ -143,7 +342,7 @@ pub async fn test_dirs<T: IntoIterator<Item = PathBuf>>(
     info!(
         "All tests complete: {passed_cases}/{total_cases} case(s) passed; {submissions_with_issues} submission(s) with failures."
     );
-    Ok(ret)
+    Ok((ret, findings))
 }
 
 #[must_use]
@@ -172,7 +371,7 @@ pub async fn test_file_progress(
     semaphore: Arc<Semaphore>,
     mp: Arc<MutexGuard<'static, MultiProgress>>,
     op: Arc<Mutex<ProgressBar>>,
-) -> (PathBuf, Result<Vec<TestResult>, RunError>) {
+) -> (PathBuf, Result<Vec<TestResult>, RunError>, Vec<IllegalExpr>) {
     let progress = mp.add(ProgressBar::new_spinner());
     let permit = match semaphore.acquire().await {
         Ok(permit) => permit,
@@ -181,6 +380,7 @@ pub async fn test_file_progress(
             return (
                 path,
                 Err(RunError::CE(None, format!("Semaphore closed: {e}"))),
+                vec![],
             );
         }
     };
@@ -196,7 +396,7 @@ pub async fn test_file_progress(
         })
         .tick_strings(&config::SPINNER);
     progress.set_style(spinner_style);
-    let mut proc = match runner::from_dir(path.clone(), Some(Language::Java)).await {
+    let mut proc = match runner::from_dir(path.clone(), Some(CONFIG.lang.clone())).await {
         Some(s) => s,
         None => {
             progress.finish_and_clear();
@@ -207,6 +407,7 @@ pub async fn test_file_progress(
             return (
                 path,
                 Err(RunError::CE(None, "Runner initialization failed".into())),
+                vec![],
             );
         }
     };
@@ -215,7 +416,11 @@ pub async fn test_file_progress(
         .and_then(|name| name.to_str())
         .map(str::to_owned)
     else {
-        return (path, Err(RunError::CE(None, "Invalid filename".into())));
+        return (
+            path,
+            Err(RunError::CE(None, "Invalid filename".into())),
+            vec![],
+        );
     };
     progress.set_message(filenamestr.clone());
     progress.enable_steady_tick(Duration::from_millis(100));
@@ -226,7 +431,7 @@ pub async fn test_file_progress(
             path.display()
         );
         debug!("{e:#?}");
-        return (path, Err(e));
+        return (path, Err(e), vec![]);
     }
     progress.finish_and_clear();
     info!(
@@ -234,7 +439,25 @@ pub async fn test_file_progress(
         style("[OK]").green().bold(),
         path.display()
     );
-    let progress = mp.add(ProgressBar::new(CONFIG.testcases.len() as u64));
+    let findings = if CONFIG.bytecode {
+        bytecode::check_dir(&path).unwrap_or_else(|e| {
+            error!("Bytecode check failed for {}: {e:#}", path.display());
+            vec![]
+        })
+    } else {
+        vec![]
+    };
+    if findings.iter().any(|issue| issue.action == Action::Block) {
+        warn!(
+            "{} {} Prohibited references in compiled classes; not running.",
+            style("[SC]").bold().red(),
+            path.display()
+        );
+        drop(permit);
+        op.lock().await.inc(1);
+        return (path, Ok(vec![]), findings);
+    }
+    let progress = mp.add(ProgressBar::new(CONFIG.cases().count() as u64));
     let bar_style = ProgressStyle::default_bar()
         .template(
             "{spinner} [{elapsed_precise}] {msg} running tests [{wide_bar:.bold.cyan/blue}]({pos}/{len})",
@@ -251,7 +474,7 @@ pub async fn test_file_progress(
     let mut ret = vec![];
     let mut correct = 0;
     for i in 0..tc.len() {
-        let push = test_proc(path.clone(), &mut proc, &tc[i]).await;
+        let push = test_proc(path.clone(), &mut proc, &tc[i], i).await;
         if push.is_correct() {
             correct += 1;
         }
@@ -273,88 +496,290 @@ pub async fn test_file_progress(
         ret.push(push);
         progress.inc(1);
     }
+    if let Some(unit) = &CONFIG.unit {
+        let results = unit::test_unit(&path, unit).await;
+        progress.inc(results.len() as u64);
+        ret.extend(results);
+    }
     drop(permit);
     op.lock().await.inc(1);
     info!("{} {}", print_tr_vec(&ret), path.display());
     progress.finish_and_clear();
-    (path, Ok(ret))
+    (path, Ok(ret), findings)
 }
 
 pub async fn test_proc(
     path: PathBuf,
     proc: &mut Box<dyn Runner>,
     testcase: &'static TestCase,
+    index: usize,
 ) -> TestResult {
+    proc.args(arguments(Some(testcase))).await;
+    proc.envs(environment(Some(testcase))).await;
+    // every case starts from a pristine copy so earlier runs cannot leak into later ones.
+    let workdir = match case_workdir(&path, index, testcase).await {
+        Ok(dir) => dir,
+        Err(e) => {
+            return TestResult::Error {
+                code: -1,
+                reason: format!("failed to prepare working directory: {e:#}"),
+            };
+        }
+    };
+    proc.workdir(Some(workdir.clone())).await;
+    let executed = execute(&path, proc, testcase.input.clone()).await;
+    let files = compare_files(&workdir, testcase).await;
+    if !SIMPLEOPTS.artifacts
+        && let Err(e) = remove_dir_all(&workdir).await
+    {
+        warn!(
+            "Failed to remove working directory {}: {e}",
+            workdir.display()
+        );
+    }
+    let Execution {
+        output: out,
+        status,
+    } = match executed {
+        Ok(out) => out,
+        Err(e) => return e,
+    };
+    let exit = testcase
+        .exit
+        .as_ref()
+        .map(|expected| ExitVerdict::new(expected, &status, testcase.exit_points));
+    let diff = compare(&testcase.expected, &out);
+    if diff.count_additions() + diff.count_removals() == 0 && files.iter().all(FileVerdict::passed)
+    {
+        TestResult::Correct {
+            case: testcase,
+            output: out,
+            exit,
+            files,
+        }
+    } else {
+        TestResult::Wrong {
+            case: testcase,
+            output: out,
+            diff,
+            exit,
+            files,
+        }
+    }
+}
+
+/// Line diff between what was expected and what the program produced.
+#[must_use]
+pub fn compare(expected: &str, actual: &str) -> Diff {
+    let input = InternedInput::new(expected, actual);
+    Diff::compute(Algorithm::Histogram, &input)
+}
+
+/// Creates a fresh copy of the compiled venv for one case, then adds the case's fixtures and
+/// input files.
+async fn case_workdir(venv: &Path, index: usize, testcase: &TestCase) -> Result<PathBuf> {
+    let dir = fresh_workdir(venv, &index.to_string()).await?;
+    for fixture in &testcase.fixtures {
+        let Some(file_name) = fixture.file_name() else {
+            warn!(
+                "Fixture {} does not have a valid filename; skipping.",
+                fixture.display()
+            );
+            continue;
+        };
+        let target = dir.join(file_name);
+        if fixture.is_dir() {
+            copy_dir(fixture, &target).await
+        } else {
+            copy(fixture, &target).await.map(|_| ())
+        }
+        .with_context(|| format!("failed to copy fixture {}", fixture.display()))?;
+    }
+    for (file, contents) in &testcase.files {
+        let target = case_file(&dir, file)?;
+        if let Some(parent) = target.parent() {
+            create_dir_all(parent).await?;
+        }
+        write(&target, contents)
+            .await
+            .with_context(|| format!("failed to write input file {file}"))?;
+    }
+    Ok(dir)
+}
+
+/// Copies the compiled venv into a new directory named after the submission and `label`.
+pub async fn fresh_workdir(venv: &Path, label: &str) -> Result<PathBuf> {
+    let name = venv
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("submission");
+    let dir = TEMPDIR.join(".cases").join(format!("{name}-{label}"));
+    if dir.exists() {
+        remove_dir_all(&dir).await?;
+    }
+    create_dir_all(&dir).await?;
+    copy_dir(venv, &dir).await?;
+    Ok(dir)
+}
+
+/// Resolves a case file name inside `dir`, refusing anything that would escape it.
+fn case_file(dir: &Path, name: &str) -> Result<PathBuf> {
+    let relative = Path::new(name);
+    if relative
+        .components()
+        .any(|part| !matches!(part, std::path::Component::Normal(_)))
+    {
+        anyhow::bail!("case file `{name}` must be a relative path inside the working directory");
+    }
+    Ok(dir.join(relative))
+}
+
+async fn compare_files(dir: &Path, testcase: &TestCase) -> Vec<FileVerdict> {
+    let mut ret = vec![];
+    for (name, expected) in &testcase.output_files {
+        let outcome = match case_file(dir, name) {
+            Err(e) => FileOutcome::Unreadable {
+                reason: e.to_string(),
+            },
+            Ok(target) => match read_to_string(&target).await {
+                Ok(actual) => {
+                    let diff = compare(expected, &actual);
+                    if diff.count_additions() + diff.count_removals() == 0 {
+                        FileOutcome::Match
+                    } else {
+                        FileOutcome::Mismatch {
+                            additions: diff.count_additions() as usize,
+                            removals: diff.count_removals() as usize,
+                            output: actual,
+                        }
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => FileOutcome::Missing,
+                Err(e) => FileOutcome::Unreadable {
+                    reason: e.to_string(),
+                },
+            },
+        };
+        ret.push(FileVerdict {
+            name: name.clone(),
+            expected: expected.clone(),
+            outcome,
+        });
+    }
+    ret
+}
+
+/// Runs the program once with `input` on stdin and collects its stdout and exit status.
+///
+/// Failures are reported as [`TestResult::Error`] so callers can forward them as-is.
+pub async fn execute(
+    path: &Path,
+    proc: &mut Box<dyn Runner>,
+    input: String,
+) -> Result<Execution, TestResult> {
     let timeout = match config::get_config() {
         Ok(cfg) => cfg.timeout,
         Err(e) => {
             error!("Failed to load configuration: {e}");
-            return TestResult::Error {
+            return Err(TestResult::Error {
                 code: -1,
                 reason: format!("configuration error: {e}"),
-            };
+            });
         }
     };
+    execute_timeout(path, proc, input, timeout).await
+}
+
+/// [`execute`] with an explicit timeout in milliseconds instead of the configured one.
+pub async fn execute_timeout(
+    path: &Path,
+    proc: &mut Box<dyn Runner>,
+    input: String,
+    timeout: u64,
+) -> Result<Execution, TestResult> {
     if let Err(e) = proc.run().await {
         let (code, reason) = match e {
             RunError::CE(code, reason) | RunError::RE(code, reason) => (code.unwrap_or(-1), reason),
         };
-        return TestResult::Error { code, reason };
+        return Err(TestResult::Error { code, reason });
     }
-    if let Err(e) = proc.stdin(testcase.input.clone()).await {
+    if let Err(e) = proc.stdin(input).await {
         let reason = format!(
             "failed to input stdin for process {}: {e}",
             path.to_string_lossy()
         );
         error!("{reason}");
-        return TestResult::Error { code: -1, reason };
+        return Err(TestResult::Error { code: -1, reason });
     }
-    if tokio::time::timeout(Duration::from_millis(timeout), proc.wait())
-        .await
-        .is_err()
-    {
-        let filename = path
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("<unknown>");
-        info!(
-            "{} has been running for too long. Killing process...",
-            filename
-        );
-        #[cfg(unix)]
-        if let Err(e) = proc.signal(nix::sys::signal::Signal::SIGKILL).await {
-            error!("failed to kill process: {e}")
+    let status = match tokio::time::timeout(Duration::from_millis(timeout), proc.wait()).await {
+        Ok(Ok(status)) => status,
+        Ok(Err(e)) => {
+            return Err(TestResult::Error {
+                code: -1,
+                reason: format!("failed to wait for process: {e}"),
+            });
         }
-        while proc.running().await {
-            tokio::time::sleep(Duration::from_millis(50)).await;
+        Err(_) => {
+            let filename = path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("<unknown>");
+            info!(
+                "{} has been running for too long. Killing process...",
+                filename
+            );
+            #[cfg(unix)]
+            if let Err(e) = proc.signal(nix::sys::signal::Signal::SIGKILL).await {
+                error!("failed to kill process: {e}")
+            }
+            while proc.running().await {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+            return Err(TestResult::Error {
+                code: 9,
+                reason: "Timed out.".into(),
+            });
         }
-        return TestResult::Error {
-            code: 9,
-            reason: "Timed out.".into(),
-        };
+    };
+
+    let output = proc.read_all().await.map_err(|e| TestResult::Error {
+        code: -1,
+        reason: format!("failed to read stdout: {e}"),
+    })?;
+    Ok(Execution { output, status })
+}
+
+/// Program arguments for `case`: the global `args` followed by the case's own.
+#[must_use]
+pub fn arguments(case: Option<&TestCase>) -> Vec<String> {
+    let mut ret = CONFIG.args.clone();
+    if let Some(case) = case {
+        ret.extend(case.args.iter().cloned());
     }
+    ret
+}
 
-    let out = match proc.read_all().await {
-        Ok(data) => data,
-        Err(e) => {
-            return TestResult::Error {
-                code: -1,
-                reason: format!("failed to read stdout: {e}"),
-            };
-        }
-    };
-    let input = InternedInput::new(testcase.expected.as_str(), out.as_str());
-    let diff = imara_diff::Diff::compute(Algorithm::Histogram, &input);
-    if diff.count_additions() + diff.count_removals() == 0 {
-        TestResult::Correct {
-            case: testcase,
-            output: out,
-        }
-    } else {
-        TestResult::Wrong {
-            case: testcase,
-            output: out,
-            diff,
+/// Environment for `case`. Starts from fixed defaults so runs are reproducible, then adds
+/// variables explicitly inherited from the grader, the global `env` and the case's `env`.
+#[must_use]
+pub fn environment(case: Option<&TestCase>) -> HashMap<String, String> {
+    let mut ret: HashMap<String, String> = runner::BASE_ENV
+        .iter()
+        .map(|(k, v)| ((*k).to_owned(), (*v).to_owned()))
+        .collect();
+    for name in CONFIG
+        .inherit_env
+        .iter()
+        .map(String::as_str)
+        .chain(["PATH"])
+    {
+        if let Ok(value) = std::env::var(name) {
+            ret.insert(name.to_owned(), value);
         }
     }
+    ret.extend(CONFIG.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+    if let Some(case) = case {
+        ret.extend(case.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+    ret
 }
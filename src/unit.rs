@@ -0,0 +1,352 @@
+use crate::config::SIMPLEOPTS;
+use crate::lang::java::JavaRunner;
+use crate::lang::python::PythonRunner;
+use crate::lang::runner::{RunError, Runner};
+use crate::test::{TestCase, TestResult, environment, execute_timeout, fresh_workdir};
+use log::{debug, error, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::hash::{BuildHasher, RandomState};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::{remove_dir_all, write};
+
+/// Prefix of every result line the harnesses print.
+const MARKER: &str = "@@BESTEST@@";
+
+/// A random token for one harness run. The harness reads it from stdin and prints it on every
+/// result line, so lines the code under test prints itself are not taken as results.
+fn nonce() -> String {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos());
+    // every `RandomState` is seeded with fresh random keys.
+    let halves = [0u8, 1].map(|half| RandomState::new().hash_one((time, half)));
+    format!("{:016x}{:016x}", halves[0], halves[1])
+}
+
+/// Language the teacher's tests are written in, which decides the harness that runs them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Suite {
+    Java,
+    Python,
+}
+
+impl Suite {
+    /// All tests must share one language; mixing them is rejected.
+    fn of(tests: &[PathBuf]) -> Option<Self> {
+        let mut suites =
+            tests
+                .iter()
+                .map(|test| match test.extension().and_then(|ext| ext.to_str()) {
+                    Some("java") => Some(Self::Java),
+                    Some("py") => Some(Self::Python),
+                    _ => None,
+                });
+        let first = suites.next()??;
+        suites.all(|suite| suite == Some(first)).then_some(first)
+    }
+
+    /// File name and source of the bundled harness.
+    const fn harness(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Java => (
+                "BestestHarness.java",
+                include_str!("lang/harness/BestestHarness.java"),
+            ),
+            Self::Python => (
+                "bestest_harness.py",
+                include_str!("lang/harness/bestest_harness.py"),
+            ),
+        }
+    }
+
+    /// Id the harness reports when a whole test file fails to load.
+    fn load_failure(self, unit: &str) -> String {
+        match self {
+            Self::Java => format!("{unit}.<class>"),
+            Self::Python => format!("{unit}.<module>"),
+        }
+    }
+
+    /// Ids of the test methods in `source`, in the order the harness runs them.
+    fn methods(self, unit: &str, source: &str) -> Vec<String> {
+        let mut ret = match self {
+            // the harness runs `void` methods only, in any order of modifiers.
+            Self::Java => {
+                let method = Regex::new(r"(?m)^\s*(?:(?:public|protected|private|static|final|synchronized|strictfp)\s+)*void\s+(test\w*)\s*\(\s*\)")
+                    .expect("static regex");
+                method
+                    .captures_iter(source)
+                    .filter_map(|caps| caps.get(1))
+                    .map(|m| format!("{unit}.{}", m.as_str()))
+                    .collect::<Vec<_>>()
+            }
+            Self::Python => {
+                let class = Regex::new(r"^class\s+(\w+)\s*\(").expect("static regex");
+                let method = Regex::new(r"^\s+(?:async\s+)?def\s+(test\w*)\s*\(\s*self\s*\)")
+                    .expect("static regex");
+                let mut current = None;
+                let mut found = vec![];
+                for line in source.lines() {
+                    if let Some(caps) = class.captures(line) {
+                        current = caps.get(1).map(|m| m.as_str());
+                    } else if line.starts_with(|c: char| !c.is_whitespace() && c != '#') {
+                        current = None;
+                    } else if let (Some(class), Some(caps)) = (current, method.captures(line)) {
+                        found.push(format!("{unit}.{class}.{}", &caps[1]));
+                    }
+                }
+                found
+            }
+        };
+        ret.sort_unstable();
+        ret.dedup();
+        ret
+    }
+
+    async fn runner(self, dir: PathBuf, harness: PathBuf) -> Result<Box<dyn Runner>, String> {
+        Ok(match self {
+            Self::Java => Box::new(
+                JavaRunner::new_from_venv(dir, harness)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            ),
+            Self::Python => Box::new(
+                PythonRunner::new_from_venv(dir, harness)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            ),
+        })
+    }
+}
+
+/// Method-level grading with teacher-provided tests.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[non_exhaustive]
+pub struct UnitConfig {
+    /// Test sources compiled together with each submission.
+    pub tests: Vec<PathBuf>,
+    /// Points per test keyed by `Class.method` for Java or `module.Class.method` for Python.
+    /// Tests not listed are worth one point.
+    #[serde(default)]
+    pub points: HashMap<String, u64>,
+    /// Tests found in `tests`, filled in by [`UnitConfig::discover`].
+    #[serde(skip)]
+    pub cases: Vec<TestCase>,
+}
+
+impl UnitConfig {
+    /// Finds the test methods in every test source so they can be scored before anything runs.
+    pub fn discover(&mut self) {
+        self.cases.clear();
+        let Some(suite) = Suite::of(&self.tests) else {
+            error!("Unit tests must be all Java or all Python sources.");
+            return;
+        };
+        for test in &self.tests {
+            let Some(unit) = test.file_stem().and_then(|stem| stem.to_str()) else {
+                warn!(
+                    "Unit test {} has no usable file name; skipping.",
+                    test.display()
+                );
+                continue;
+            };
+            let source = match read_to_string(test) {
+                Ok(source) => source,
+                Err(e) => {
+                    error!("Failed to read unit test {}: {e}", test.display());
+                    continue;
+                }
+            };
+            for name in suite.methods(unit, &source) {
+                self.cases.push(TestCase {
+                    points: self.points.get(&name).copied().unwrap_or(1),
+                    name: Some(name),
+                    ..TestCase::default()
+                });
+            }
+        }
+        for name in self.points.keys() {
+            if !self
+                .cases
+                .iter()
+                .any(|case| case.name.as_ref() == Some(name))
+            {
+                warn!("Points configured for unknown unit test `{name}`.");
+            }
+        }
+        debug!("Discovered {} unit test(s).", self.cases.len());
+    }
+}
+
+/// One line of harness output.
+#[derive(Debug)]
+struct Report {
+    id: String,
+    status: String,
+    exception: String,
+    message: String,
+}
+
+/// Runs the teacher's tests against the submission compiled in `venv`.
+///
+/// Returns one result per discovered test, in the order of [`UnitConfig::cases`].
+pub async fn test_unit(venv: &Path, unit: &'static UnitConfig) -> Vec<TestResult> {
+    if unit.cases.is_empty() {
+        return vec![];
+    }
+    let Some(suite) = Suite::of(&unit.tests) else {
+        return vec![TestResult::Error {
+            code: -1,
+            reason: "unit tests must be all Java or all Python sources".into(),
+        }];
+    };
+    match run_harness(venv, unit, suite).await {
+        Ok((nonce, output)) => collect(unit, suite, &parse(&output, &nonce)),
+        Err(TestResult::Error { code, reason }) => unit
+            .cases
+            .iter()
+            .map(|_| TestResult::Error {
+                code,
+                reason: reason.clone(),
+            })
+            .collect(),
+        Err(other) => vec![other],
+    }
+}
+
+/// Runs the harness, returning the nonce it was given along with its output.
+async fn run_harness(
+    venv: &Path,
+    unit: &UnitConfig,
+    suite: Suite,
+) -> Result<(String, String), TestResult> {
+    let error = |reason: String| TestResult::Error { code: -1, reason };
+    let timeout = crate::config::CONFIG.timeout;
+    if timeout == 0 {
+        // the whole run gets `timeout` per test, so it would be killed right away.
+        return Err(error("unit tests need a nonzero `timeout`".into()));
+    }
+    let dir = fresh_workdir(venv, "unit")
+        .await
+        .map_err(|e| error(format!("failed to prepare unit test directory: {e:#}")))?;
+    let (name, source) = suite.harness();
+    let harness = dir.join(name);
+    write(&harness, source)
+        .await
+        .map_err(|e| error(format!("failed to write test harness: {e}")))?;
+    let mut proc = suite.runner(dir.clone(), harness).await.map_err(error)?;
+    proc.add_deps(unit.tests.clone()).await.map_err(error)?;
+    if let Err(RunError::CE(code, reason) | RunError::RE(code, reason)) = proc.prepare().await {
+        return Err(TestResult::Error {
+            code: code.unwrap_or(-1),
+            reason,
+        });
+    }
+    let mut args = vec![format!("--timeout={timeout}")];
+    args.extend(
+        unit.tests
+            .iter()
+            .filter_map(|test| test.file_stem().and_then(|stem| stem.to_str()))
+            .map(str::to_owned),
+    );
+    proc.args(args).await;
+    proc.envs(environment(None)).await;
+    let budget = timeout.saturating_mul(unit.cases.len() as u64 + 1);
+    let nonce = nonce();
+    let executed = execute_timeout(&dir, &mut proc, format!("{nonce}\n"), budget).await;
+    if !SIMPLEOPTS.artifacts
+        && let Err(e) = remove_dir_all(&dir).await
+    {
+        warn!(
+            "Failed to remove unit test directory {}: {e}",
+            dir.display()
+        );
+    }
+    executed.map(|execution| (nonce, execution.output))
+}
+
+/// Result lines of the harness run given `nonce`; other lines are ignored.
+fn parse(output: &str, nonce: &str) -> Vec<Report> {
+    output
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix(MARKER)?
+                .strip_prefix('\t')?
+                .strip_prefix(nonce)?
+                .strip_prefix('\t')
+        })
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t').map(unescape);
+            Some(Report {
+                id: fields.next()?,
+                status: fields.next()?,
+                exception: fields.next().unwrap_or_default(),
+                message: fields.next().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+fn unescape(field: &str) -> String {
+    let mut ret = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ret.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => ret.push('\t'),
+            Some('n') => ret.push('\n'),
+            Some('r') => ret.push('\r'),
+            Some(other) => ret.push(other),
+            None => ret.push('\\'),
+        }
+    }
+    ret
+}
+
+fn collect(unit: &'static UnitConfig, suite: Suite, reports: &[Report]) -> Vec<TestResult> {
+    unit.cases
+        .iter()
+        .map(|case| {
+            let name = case.name.as_deref().unwrap_or_default();
+            let file = suite.load_failure(name.split('.').next().unwrap_or_default());
+            // a test file that failed to load reports once for all of its tests.
+            let mut matching = reports.iter().filter(|report| report.id == name);
+            let report = matching
+                .next()
+                .or_else(|| reports.iter().find(|report| report.id == file));
+            if matching.next().is_some() {
+                // the harness reports every test once; more results mean something else wrote them.
+                return TestResult::Failed {
+                    case,
+                    exception: None,
+                    message: "the test reported more than one result".into(),
+                };
+            }
+            match report {
+                Some(report) if report.status == "PASS" => TestResult::Correct {
+                    case,
+                    output: String::new(),
+                    exit: None,
+                    files: vec![],
+                },
+                Some(report) => TestResult::Failed {
+                    case,
+                    exception: Some(report.exception.clone()).filter(|e| !e.is_empty()),
+                    message: report.message.clone(),
+                },
+                None => TestResult::Failed {
+                    case,
+                    exception: None,
+                    message: "the test did not report a result".into(),
+                },
+            }
+        })
+        .collect()
+}
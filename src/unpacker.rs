@@ -299,6 +299,15 @@ pub fn find_in_dir(p: &PathBuf, target: &str) -> Option<PathBuf> {
     for entry in WalkDir::new(p) {
         match entry {
             Ok(dir_entry) => {
+                // compiled artifacts share the entry's name but are never entry points.
+                let known = dir_entry
+                    .path()
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| KNOWN_EXTENSIONS.contains(ext));
+                if !known {
+                    continue;
+                }
                 let name = match dir_entry.file_name().to_str() {
                     Some(name) => name.to_lowercase(),
                     None => continue,
@@ -312,3 +321,20 @@ pub fn find_in_dir(p: &PathBuf, target: &str) -> Option<PathBuf> {
     }
     None
 }
+
+/// Recursively copies the contents of `from` into `to`, creating directories as needed.
+pub async fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    for entry in WalkDir::new(from) {
+        let entry = entry.map_err(std::io::Error::other)?;
+        let Ok(relative) = entry.path().strip_prefix(from) else {
+            continue;
+        };
+        let target = to.join(relative);
+        if entry.file_type().is_dir() {
+            tokio::fs::create_dir_all(&target).await?;
+        } else {
+            copy(entry.path(), &target).await?;
+        }
+    }
+    Ok(())
+}